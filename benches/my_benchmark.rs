@@ -1,17 +1,22 @@
-use std::{fs::File, io::BufReader};
-
 use autocompletion::{
-    index::{basic::BasicIndex, japanese::JapaneseIndex, SuggestionIndex},
+    index::{
+        basic::BasicIndex, file::IndexFile, japanese::JapaneseIndex, mmap::MmapIndex,
+        SuggestionIndex,
+    },
     suggest::{query::SuggestionQuery, task::SuggestionTask},
 };
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
 
 pub fn get_jp_index() -> JapaneseIndex {
-    bincode::deserialize_from(BufReader::new(File::open("./new_jp_index").unwrap())).unwrap()
+    JapaneseIndex::load_file("./new_jp_index").unwrap()
 }
 
 fn get_index() -> BasicIndex {
-    bincode::deserialize_from(BufReader::new(File::open("./index").unwrap())).unwrap()
+    BasicIndex::load_file("./index").unwrap()
+}
+
+fn get_mmap_index() -> MmapIndex {
+    MmapIndex::open("./index.mmap").unwrap()
 }
 
 fn criterion_benchmark(c: &mut Criterion) {
@@ -36,6 +41,17 @@ fn criterion_benchmark(c: &mut Criterion) {
         })
     });
 
+    let mmap_engine = get_mmap_index();
+    c.bench_function("mmap simple query 2", |b| {
+        let term = "musi";
+        b.iter(|| {
+            let mut task = SuggestionTask::new(30);
+            task.add_query(SuggestionQuery::new(&mmap_engine, black_box(term)));
+            let res = task.search();
+            assert!(res.len() > 0);
+        })
+    });
+
     c.bench_function("simple query", |b| {
         let term = "to ";
         b.iter(|| {
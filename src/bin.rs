@@ -12,17 +12,21 @@ use autocompletion::{
     index::{
         self,
         basic::{basic_format, builder::BasicIndexBuilder, BasicIndex},
+        file::IndexFile,
         japanese::JapaneseIndex,
+        mmap::MmapIndex,
         ngram::{builder::NgramIndexBuilder, NgramIndex},
         str_item::StringItem,
         IndexItem, KanjiReadingAlign, NGIndexable, SuggestionIndex,
     },
+    normalize::NormalizerPipeline,
     suggest::{
         extension::{
             custom::CustomExtension, kanji_align::KanjiAlignExtension,
             longest_prefix::LongestPrefixExtension, ngram::NGramExtension,
             similar_terms::SimilarTermsExtension,
         },
+        pipeline::{QueryTemplate, SuggestionPipeline},
         query::SuggestionQuery,
         task::SuggestionTask,
     },
@@ -30,34 +34,39 @@ use autocompletion::{
 
 pub fn main() {
     /* let index = load_jp();
-    let index: JapaneseIndex =
-        bincode::deserialize_from(BufReader::new(File::open("./kanji_meanings").unwrap())).unwrap(); */
+    let index =
+        JapaneseIndex::load_legacy(BufReader::new(File::open("./kanji_meanings").unwrap())).unwrap(); */
     let index = build();
-    bincode::serialize_into(BufWriter::new(File::create("index").unwrap()), &index).unwrap();
+    index.save_file("index").unwrap();
+    index
+        .write_mmap(File::create("index.mmap").unwrap())
+        .unwrap();
     /*
     let index = build_ng();
-    bincode::serialize_into(BufWriter::new(File::create("index").unwrap()), &index).unwrap();
+    index.save_file("index").unwrap();
     */
 
     println!("Index loaded ({})", index.len());
 
+    let pipeline = build_pipeline(&index);
+
     let mut s = String::new();
     loop {
         std::io::stdin().read_line(&mut s).unwrap();
         s = s.replace("\n", "");
 
-        search(&index, &s);
+        search(&pipeline, &s);
 
         s.clear();
     }
 }
 
-fn search<T: SuggestionIndex + NGIndexable + 'static>(engine: &T, query: &str) {
-    let query = query.to_lowercase();
-    let start = Instant::now();
-    let mut task = SuggestionTask::new(30).debug();
+fn build_pipeline<T: SuggestionIndex + NGIndexable + Sync + 'static>(
+    engine: &T,
+) -> SuggestionPipeline {
+    let mut pipeline = SuggestionPipeline::new(30).explain();
 
-    let mut query = SuggestionQuery::new(engine, query);
+    let mut query = QueryTemplate::new(engine);
     query.weights.str_weight = 1.4;
     query.weights.freq_weight = 0.6;
 
@@ -86,8 +95,13 @@ fn search<T: SuggestionIndex + NGIndexable + 'static>(engine: &T, query: &str) {
     //ngext.options.weights.total_weight = 0.8;
     query.add_extension(ngext);
 
-    task.add_query(query);
-    let completions = task.search();
+    pipeline.add_query(query);
+    pipeline
+}
+
+fn search(pipeline: &SuggestionPipeline, query: &str) {
+    let start = Instant::now();
+    let completions = pipeline.search_results(query);
     let end = start.elapsed();
 
     println!("{:#?}", completions);
@@ -100,13 +114,13 @@ pub fn build_ng() -> NgramIndex {
     let freq_data = load_freq_list();
 
     let mut builder = NgramIndexBuilder::new(3);
+    builder.set_normalizer(NormalizerPipeline::basic());
 
     for term in terms {
         let freq = freq_data.get(&term).unwrap_or(&0.0);
-        let term_fmt = basic_format(&term);
-        let item = index::ngram::Item::new(term, 0, *freq);
+        let item = index::ngram::Item::new(term.clone(), 0, *freq);
 
-        builder.insert(&[term_fmt], item);
+        builder.insert(&[term], item);
     }
 
     builder.build()
@@ -117,12 +131,13 @@ pub fn build() -> BasicIndex {
     let freq_data = load_freq_list();
 
     let mut builder = BasicIndexBuilder::new();
+    builder.set_normalizer(NormalizerPipeline::basic_unicode());
+    builder.set_accent_insensitive(true);
 
     for term in terms {
         let freq = freq_data.get(&term).unwrap_or(&0.0);
-        let formatted = basic_format(&term);
-        let item = index::basic::Item::new(term, 0, *freq);
-        builder.insert(item, &formatted);
+        let item = index::basic::Item::new(term.clone(), 0, *freq);
+        builder.insert(item, &term);
     }
 
     builder.build()
@@ -167,6 +182,9 @@ fn all_docs() -> Vec<String> {
 }
 
 pub fn load_jp() -> JapaneseIndex {
-    let file = File::open("./new_jp_index").unwrap();
-    bincode::deserialize_from(BufReader::new(file)).unwrap()
+    JapaneseIndex::load_file("./new_jp_index").unwrap()
+}
+
+pub fn load_mmap() -> MmapIndex {
+    MmapIndex::open("./index.mmap").unwrap()
 }
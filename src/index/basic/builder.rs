@@ -1,13 +1,21 @@
 use super::{BasicIndex, Item};
+use crate::{
+    index::{prefix_tree::PrefixTreeBuilder, WordIdMap},
+    normalize::{fold, Normalizer, NormalizerPipeline},
+};
 use ngindex::builder::NGIndexBuilder;
-use qp_trie::{wrapper::BString, Trie};
+use std::collections::HashSet;
 
 pub struct BasicIndexBuilder {
     build_ng_index: bool,
     ng_index: NGIndexBuilder<u32>,
 
     items: Vec<Item>,
-    trie: Trie<BString, u32>,
+    /// Normalized terms inserted so far, to reject duplicates
+    keys: HashSet<String>,
+    tree: PrefixTreeBuilder,
+    normalizer: NormalizerPipeline,
+    fold_diacritics: bool,
 }
 
 impl BasicIndexBuilder {
@@ -17,7 +25,10 @@ impl BasicIndexBuilder {
             // some dummy value we don't care since we don't use this
             ng_index: NGIndexBuilder::new(10),
             items: vec![],
-            trie: Trie::new(),
+            keys: HashSet::new(),
+            tree: PrefixTreeBuilder::new(),
+            normalizer: NormalizerPipeline::default(),
+            fold_diacritics: false,
         }
     }
 
@@ -26,21 +37,46 @@ impl BasicIndexBuilder {
             build_ng_index: true,
             ng_index: NGIndexBuilder::new(n),
             items: vec![],
-            trie: Trie::new(),
+            keys: HashSet::new(),
+            tree: PrefixTreeBuilder::new(),
+            normalizer: NormalizerPipeline::default(),
+            fold_diacritics: false,
         }
     }
 
+    /// Sets the normalizer that gets applied to all inserted terms. The index keeps it to
+    /// normalize queries the same way
+    pub fn set_normalizer(&mut self, normalizer: NormalizerPipeline) {
+        self.normalizer = normalizer;
+    }
+
+    /// Enables accent insensitive matching. Each term additionally gets indexed without
+    /// diacritics (über -> uber, ueber; straße -> strasse), so predictions and exact lookups
+    /// find it either way. Use a normalizer containing `Step::Nfkc` to also match full-width
+    /// input and ligatures.
+    pub fn set_accent_insensitive(&mut self, enabled: bool) {
+        self.fold_diacritics = enabled;
+    }
+
     /// Returns Ok(u32) with the ID of the newly inserted item. Err(Item)
     // with the passed item if the term already existed
     pub fn insert(&mut self, item: Item, formatted: &str) -> Result<u32, Item> {
         let id = self.items.len() as u32;
+        let formatted = self.normalizer.normalize(formatted);
 
-        if self.trie.contains_key_str(formatted) {
+        if self.keys.contains(&formatted) {
             return Err(item);
         }
 
         self.items.push(item);
-        self.trie.insert_str(formatted, id);
+        self.tree.insert(&formatted, id);
+        self.keys.insert(formatted.clone());
+
+        if self.fold_diacritics {
+            for variant in fold::fold_variants(&formatted) {
+                self.tree.insert(&variant, id);
+            }
+        }
 
         Ok(id)
     }
@@ -49,15 +85,21 @@ impl BasicIndexBuilder {
         if !self.build_ng_index {
             return;
         }
-        self.ng_index.insert(formatted, id);
+        let formatted = self.normalizer.normalize(formatted);
+        self.ng_index.insert(&formatted, id);
     }
 
     pub fn build(self) -> BasicIndex {
         let ngram = self.ng_index.build();
+        let items = self.items;
+        let tree = self.tree.build(|id| items[id as usize].frequency);
         BasicIndex {
-            trie: self.trie,
-            terms: self.items,
+            tree,
+            terms: items,
             ngram,
+            normalizer: self.normalizer,
+            fold_diacritics: self.fold_diacritics,
+            word_ids: WordIdMap::default(),
         }
     }
 }
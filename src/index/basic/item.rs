@@ -2,6 +2,7 @@ use serde::{Deserialize, Serialize};
 
 use crate::{
     index::{Output, ToOutput},
+    normalize::fold,
     relevance::item::EngineItem,
 };
 
@@ -44,10 +45,7 @@ impl Item {
 impl ToOutput for Item {
     #[inline]
     fn to_output(&self) -> Output {
-        Output {
-            primary: self.word.clone(),
-            secondary: None,
-        }
+        Output::new(self.word.clone(), None)
     }
 }
 
@@ -59,14 +57,7 @@ impl super::super::IndexItem for Item {
 
     #[inline]
     fn str_relevance(&self, query: &str) -> u16 {
-        let query = query.to_lowercase();
-        if self.word.to_lowercase().starts_with(&query) {
-            // Give shorter matches more priority. For exact matches (lame length) => normalized=0
-            let normalized = 1.0 - (query.len() as f32 / self.word.len() as f32);
-            1000 - (normalized * 1000.0) as u16
-        } else {
-            (strsim::normalized_levenshtein(&self.word, &query) * 100.0) as u16
-        }
+        word_relevance(&self.word, query)
     }
 
     #[inline]
@@ -75,8 +66,8 @@ impl super::super::IndexItem for Item {
     }
 
     #[inline]
-    fn terms(&self) -> Vec<&String> {
-        vec![&self.word]
+    fn terms(&self) -> Vec<&str> {
+        vec![self.word.as_str()]
     }
 
     #[inline]
@@ -84,3 +75,32 @@ impl super::super::IndexItem for Item {
         self.word_id
     }
 }
+
+/// Penalty for words that only match the query if diacritics are ignored
+const FOLDED_MATCH_PENALTY: u16 = 50;
+
+/// String relevance of a `word` for the given `query`
+#[inline]
+pub(crate) fn word_relevance(word: &str, query: &str) -> u16 {
+    let query = query.to_lowercase();
+    let word = word.to_lowercase();
+    if word.starts_with(&query) {
+        return prefix_relevance(&query, &word);
+    }
+
+    let folded_query = fold::fold_diacritics(&query);
+    for variant in fold::fold_variants(&word) {
+        if variant.starts_with(&folded_query) {
+            return prefix_relevance(&folded_query, &variant).saturating_sub(FOLDED_MATCH_PENALTY);
+        }
+    }
+
+    (strsim::normalized_levenshtein(&word, &query) * 100.0) as u16
+}
+
+#[inline]
+fn prefix_relevance(query: &str, word: &str) -> u16 {
+    // Give shorter matches more priority. For exact matches (lame length) => normalized=0
+    let normalized = 1.0 - (query.len() as f32 / word.len() as f32);
+    1000 - (normalized * 1000.0) as u16
+}
@@ -3,24 +3,42 @@ pub mod item;
 
 pub use item::Item;
 use ngindex::NGIndex;
-use order_struct::{float_ord::FloatOrd, order_nh::OrderVal, OrderBy};
+use order_struct::{float_ord::FloatOrd, order_nh::OrderVal};
 
-use super::{IndexItem, NGIndexable, SuggestionIndex};
-use crate::relevance::item::EngineItem;
+use super::{
+    file::IndexFile,
+    mmap::MmapWriter,
+    prefix_tree::{Levenshtein, PrefixTree, TreeView, EDIT_COST},
+    IndexItem, IndexKind, NGIndexable, SuggestionIndex, WordIdMap,
+};
+use crate::{
+    normalize::{fold, Normalizer, NormalizerPipeline},
+    relevance::item::EngineItem,
+};
 use priority_container::{PrioContainer, PrioContainerMax};
-use qp_trie::{wrapper::BString, Trie};
 use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashSet,
+    io::{self, Write},
+};
 
 /// Index with basic suggestion functionality
 #[derive(Serialize, Deserialize)]
 pub struct BasicIndex {
-    /// Prefix tree to quickly find possible suggestion. The trees value is the ID/Position
-    /// of the word in the `terms` vector
-    trie: Trie<BString, u32>,
+    /// Character tree over all terms, used for exact, prefix and top-k lookups. Values are
+    /// the ID/Position of the word in the `terms` vector
+    tree: PrefixTree,
     /// All Words, with the vector position as ID and frequency data
     terms: Vec<Item>,
 
     ngram: NGIndex<u32>,
+
+    /// Normalizer that was applied to all terms
+    normalizer: NormalizerPipeline,
+    /// Whether terms were additionally indexed without diacritics
+    fold_diacritics: bool,
+    #[serde(skip)]
+    word_ids: WordIdMap,
 }
 
 impl BasicIndex {
@@ -29,34 +47,33 @@ impl BasicIndex {
     fn get_item(&self, id: u32) -> &Item {
         &self.terms[id as usize]
     }
+
+    /// Writes the index in a format that can be memory mapped using `MmapIndex`
+    pub fn write_mmap<W: Write>(&self, out: W) -> io::Result<()> {
+        let mut writer = MmapWriter::new(IndexKind::Basic);
+        writer.set_normalizer(self.normalizer.clone());
+        writer.set_accent_insensitive(self.fold_diacritics);
+        for item in &self.terms {
+            writer.add_item(item.word_id, item.frequency, item.priority(), &[&item.word]);
+        }
+        writer.write(self.tree.view(), out)
+    }
 }
 
 impl SuggestionIndex for BasicIndex {
     fn predictions(&self, inp: &str, limit: usize) -> Vec<EngineItem> {
-        let mut prio_container = PrioContainerMax::new_allocated(limit);
-
-        let items = self.trie.iter_prefix_str(inp).map(|i| {
-            OrderBy::new(self.get_item(*i.1), |a, b| {
-                FloatOrd(a.frequency()).cmp(&FloatOrd(b.frequency()))
-            })
-        });
-
-        prio_container.extend(items);
-
-        // PrioContainer only yields `limit` items
-        prio_container
+        let freq = |id: u32| self.get_item(id).frequency;
+        prefix_lookup(self.tree.view(), inp, limit, self.fold_diacritics, freq)
             .into_iter()
-            .map(|i| i.0.into_inner().into_engine_item())
+            .map(|id| self.get_item(id).into_engine_item())
             .collect()
     }
 
-    #[inline]
     fn exact(&self, inp: &str) -> Vec<EngineItem> {
-        let word = match self.trie.get_str(inp).and_then(|i| self.get_word(*i)) {
-            Some(st) => st,
-            None => return vec![],
-        };
-        vec![word]
+        exact_lookup(self.tree.view(), inp, self.fold_diacritics)
+            .into_iter()
+            .filter_map(|i| self.get_word(i))
+            .collect()
     }
 
     fn similar_terms(&self, inp: &str, limit: usize, max_dist: u32) -> Vec<EngineItem> {
@@ -70,10 +87,13 @@ impl SuggestionIndex for BasicIndex {
 
         let prefix = inp.char_indices().nth(1).map(|i| &inp[0..i.0]).unwrap();
 
+        let mut seen = HashSet::new();
         let iter = self
-            .trie
-            .iter_prefix_str(prefix)
-            .map(|i| self.get_item(*i.1));
+            .tree
+            .view()
+            .prefix_ids(prefix)
+            .filter(|i| seen.insert(*i))
+            .map(|i| self.get_item(i));
 
         for term in iter {
             let hash = match &term.hash {
@@ -93,15 +113,110 @@ impl SuggestionIndex for BasicIndex {
         out.into_iter().collect()
     }
 
+    fn fuzzy_predictions(&self, inp: &str, limit: usize, max_dist: u32) -> Vec<EngineItem> {
+        self.tree
+            .view()
+            .fuzzy_top_k(inp, limit, max_dist * EDIT_COST, &Levenshtein, |id| {
+                self.get_item(id).frequency
+            })
+            .into_iter()
+            .map(|(id, dist)| EngineItem::new(self.get_item(id), dist as u16))
+            .collect()
+    }
+
     #[inline]
     fn get_word(&self, id: u32) -> Option<EngineItem> {
         Some(self.terms.get(id as usize)?.into_engine_item())
     }
 
+    fn word_by_id(&self, word_id: u32) -> Option<EngineItem> {
+        let pos = self
+            .word_ids
+            .position(word_id, || self.terms.iter().map(|i| i.word_id))?;
+        self.get_word(pos)
+    }
+
     #[inline]
     fn len(&self) -> usize {
         self.terms.len()
     }
+
+    #[inline]
+    fn normalizer(&self) -> Option<&dyn Normalizer> {
+        Some(&self.normalizer)
+    }
+}
+
+/// Returns the IDs of the `limit` most frequent terms of `tree` starting with `inp`. Trees
+/// which additionally hold their terms without diacritics (`fold_diacritics`) get searched
+/// for the folded query as well. Shared with memory mapped basic indexes
+pub(crate) fn prefix_lookup<F>(
+    tree: TreeView,
+    inp: &str,
+    limit: usize,
+    fold_diacritics: bool,
+    freq: F,
+) -> Vec<u32>
+where
+    F: Fn(u32) -> f64,
+{
+    let mut ids = tree.top_k(inp, limit, &freq);
+
+    if fold_diacritics {
+        // Also find terms without diacritics if the query has some
+        let folded = fold::fold_diacritics(inp);
+        if folded != inp {
+            ids.extend(tree.top_k(&folded, limit, &freq));
+            let mut seen = HashSet::with_capacity(ids.len());
+            ids.retain(|i| seen.insert(*i));
+            ids.sort_by(|a, b| freq(*b).total_cmp(&freq(*a)));
+            ids.truncate(limit);
+        }
+    }
+
+    ids
+}
+
+/// Returns the IDs of all terms of `tree` equal to `inp`. See `prefix_lookup` for
+/// `fold_diacritics`
+pub(crate) fn exact_lookup(tree: TreeView, inp: &str, fold_diacritics: bool) -> Vec<u32> {
+    let mut ids: Vec<u32> = tree
+        .find(inp)
+        .map(|node| tree.node_ids(node).to_vec())
+        .unwrap_or_default();
+
+    if fold_diacritics {
+        // Folded terms are part of the tree already, so "cafe" finds café. Folding the
+        // query as well lets "cafè" find it too
+        let folded = fold::fold_diacritics(inp);
+        if let Some(node) = tree.find(&folded).filter(|_| folded != inp) {
+            for id in tree.node_ids(node) {
+                if !ids.contains(id) {
+                    ids.push(*id);
+                }
+            }
+        }
+    }
+
+    ids
+}
+
+impl IndexFile for BasicIndex {
+    const KIND: IndexKind = IndexKind::Basic;
+
+    #[inline]
+    fn term_count(&self) -> usize {
+        self.terms.len()
+    }
+
+    #[inline]
+    fn normalizer_config(&self) -> Option<String> {
+        let mut config = self.normalizer.config();
+        if self.fold_diacritics {
+            config.push_str(",accent_insensitive");
+        }
+        Some(config)
+    }
 }
 
 impl NGIndexable for BasicIndex {
@@ -134,14 +249,54 @@ impl NGIndexable for BasicIndex {
     }
 }
 
-// Basic input formatting helper
+// Basic input formatting helper. Same as `NormalizerPipeline::basic()`
 pub fn basic_format(inp: &str) -> String {
-    let mut out = inp.to_string();
-    let to_replace = &[
-        "(", ")", ".", ",", "/", "[", "]", "?", "{", "}", "、", "。", "・",
-    ];
-    for tr in to_replace {
-        out = out.replace(tr, "");
-    }
-    out.to_lowercase()
+    NormalizerPipeline::basic().normalize(inp)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{builder::BasicIndexBuilder, *};
+    use crate::index::test_util::{self, word_ids};
+
+    const WORDS: &[(&str, f64)] = &[("café", 0.5), ("über", 0.4), ("cafeteria", 0.3)];
+
+    fn build(accent_insensitive: bool) -> BasicIndex {
+        test_util::basic_index(WORDS, |b| b.set_accent_insensitive(accent_insensitive))
+    }
+
+    fn exact_ids(index: &BasicIndex, inp: &str) -> Vec<u32> {
+        word_ids(&index.exact(inp))
+    }
+
+    #[test]
+    fn test_exact_folded() {
+        let index = build(true);
+        assert_eq!(exact_ids(&index, "café"), vec![0]);
+        assert_eq!(exact_ids(&index, "cafe"), vec![0]);
+        assert_eq!(exact_ids(&index, "cafè"), vec![0]);
+        assert_eq!(exact_ids(&index, "uber"), vec![1]);
+        assert_eq!(exact_ids(&index, "ueber"), vec![1]);
+        assert!(exact_ids(&index, "caf").is_empty());
+    }
+
+    #[test]
+    fn test_exact_without_folding() {
+        let index = build(false);
+        assert_eq!(exact_ids(&index, "café"), vec![0]);
+        assert!(exact_ids(&index, "cafe").is_empty());
+    }
+
+    #[test]
+    fn test_predictions_folded() {
+        let index = build(true);
+        assert_eq!(word_ids(&index.predictions("cafe", 10)), vec![0, 2]);
+    }
+
+    #[test]
+    fn test_duplicate_terms() {
+        let mut builder = BasicIndexBuilder::new();
+        assert!(builder.insert(Item::new("a".into(), 0, 0.1), "a").is_ok());
+        assert!(builder.insert(Item::new("a".into(), 1, 0.1), "a").is_err());
+    }
 }
@@ -0,0 +1,356 @@
+use std::{
+    fmt::Display,
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use super::IndexKind;
+
+/// Magic bytes at the beginning of each index file
+const MAGIC: &[u8; 4] = b"ACIX";
+
+/// Version of the file format. Has to be increased on every change of the serialized
+/// representation of an index
+pub const FORMAT_VERSION: u32 = 1;
+
+/// Magic, format version, kind, metadata length, payload length and checksum
+const HEADER_LEN: usize = 4 + 4 + 4 + 4 + 8 + 4;
+
+/// Metadata stored in the header of an index file
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct IndexMeta {
+    pub kind: IndexKind,
+    pub format_version: u32,
+    /// Version of the crate the file was written with
+    pub crate_version: String,
+    /// Unix timestamp of when the index was written
+    pub created: u64,
+    pub term_count: u64,
+    /// Configuration of the normalizer that was applied to the indexed terms
+    pub normalizer: Option<String>,
+}
+
+/// Error returned when reading or writing index files
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    /// The file is not an index file. Japanese indexes written before the index file format
+    /// existed can be loaded with `JapaneseIndex::load_legacy`
+    InvalidMagic,
+    /// The file was written with an incompatible format version
+    UnsupportedVersion(u32),
+    /// The file contains an index of unknown type
+    UnknownKind(u32),
+    /// The file contains another type of index than the one that was requested
+    KindMismatch {
+        expected: IndexKind,
+        found: IndexKind,
+    },
+    /// The file content doesn't match its checksum
+    ChecksumMismatch,
+    /// The file ended before all data announced in its header was read
+    Truncated,
+    Encoding(bincode::Error),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Io(err) => write!(f, "io error: {err}"),
+            Error::InvalidMagic => write!(f, "not an index file"),
+            Error::UnsupportedVersion(v) => write!(f, "unsupported format version {v}"),
+            Error::UnknownKind(k) => write!(f, "unknown index kind {k}"),
+            Error::KindMismatch { expected, found } => {
+                write!(f, "expected {expected:?} index but found {found:?}")
+            }
+            Error::ChecksumMismatch => write!(f, "checksum mismatch"),
+            Error::Truncated => write!(f, "unexpected end of file"),
+            Error::Encoding(err) => write!(f, "encoding error: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<io::Error> for Error {
+    #[inline]
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<bincode::Error> for Error {
+    #[inline]
+    fn from(err: bincode::Error) -> Self {
+        Self::Encoding(err)
+    }
+}
+
+/// An index that can be saved to and loaded from a versioned index file
+pub trait IndexFile: Serialize + DeserializeOwned {
+    const KIND: IndexKind;
+
+    /// Returns the amount of terms in the index
+    fn term_count(&self) -> usize;
+
+    /// Returns the configuration of the normalizer used for the index
+    #[inline]
+    fn normalizer_config(&self) -> Option<String> {
+        None
+    }
+
+    /// Returns the metadata for a new index file
+    fn meta(&self) -> IndexMeta {
+        let created = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|i| i.as_secs())
+            .unwrap_or(0);
+
+        IndexMeta {
+            kind: Self::KIND,
+            format_version: FORMAT_VERSION,
+            crate_version: env!("CARGO_PKG_VERSION").to_string(),
+            created,
+            term_count: self.term_count() as u64,
+            normalizer: self.normalizer_config(),
+        }
+    }
+
+    /// Writes the index into `out`
+    fn save<W: Write>(&self, mut out: W) -> Result<(), Error> {
+        let meta = bincode::serialize(&self.meta())?;
+        let payload = bincode::serialize(self)?;
+
+        let mut hasher = crc32fast::Hasher::new();
+        hasher.update(&meta);
+        hasher.update(&payload);
+
+        out.write_all(MAGIC)?;
+        out.write_all(&FORMAT_VERSION.to_le_bytes())?;
+        out.write_all(&Self::KIND.to_u32().to_le_bytes())?;
+        out.write_all(&(meta.len() as u32).to_le_bytes())?;
+        out.write_all(&(payload.len() as u64).to_le_bytes())?;
+        out.write_all(&hasher.finalize().to_le_bytes())?;
+        out.write_all(&meta)?;
+        out.write_all(&payload)?;
+        out.flush()?;
+        Ok(())
+    }
+
+    /// Reads an index from `inp`
+    fn load<R: Read>(mut inp: R) -> Result<Self, Error> {
+        let (_, payload) = read_sections(&mut inp, Self::KIND)?;
+        Ok(bincode::deserialize(&payload)?)
+    }
+
+    /// Reads an index together with the metadata of its file from `inp`
+    fn load_with_meta<R: Read>(mut inp: R) -> Result<(IndexMeta, Self), Error> {
+        let (meta, payload) = read_sections(&mut inp, Self::KIND)?;
+        Ok((
+            bincode::deserialize(&meta)?,
+            bincode::deserialize(&payload)?,
+        ))
+    }
+
+    /// Writes the index into a new file at `path`
+    fn save_file<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        self.save(BufWriter::new(File::create(path)?))
+    }
+
+    /// Reads the index from the file at `path`
+    fn load_file<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        Self::load(BufReader::new(File::open(path)?))
+    }
+}
+
+/// Reads the metadata of an index file without loading the index
+pub fn read_meta<R: Read>(mut inp: R) -> Result<IndexMeta, Error> {
+    let header = read_header(&mut inp)?;
+    let meta = read_len(&mut inp, header.meta_len)?;
+    Ok(bincode::deserialize(&meta)?)
+}
+
+/// Reads the metadata and payload of an index file of the given kind and verifies their
+/// checksum
+fn read_sections<R: Read>(inp: &mut R, kind: IndexKind) -> Result<(Vec<u8>, Vec<u8>), Error> {
+    let header = read_header(inp)?;
+    if header.kind != kind {
+        return Err(Error::KindMismatch {
+            expected: kind,
+            found: header.kind,
+        });
+    }
+
+    let meta = read_len(inp, header.meta_len)?;
+    let payload = read_len(inp, header.payload_len)?;
+
+    let mut hasher = crc32fast::Hasher::new();
+    hasher.update(&meta);
+    hasher.update(&payload);
+    if hasher.finalize() != header.checksum {
+        return Err(Error::ChecksumMismatch);
+    }
+
+    Ok((meta, payload))
+}
+
+/// Reads exactly `len` bytes. The buffer grows with the data actually read, so a corrupt
+/// length in the header can't cause a huge allocation
+fn read_len<R: Read>(inp: &mut R, len: u64) -> Result<Vec<u8>, Error> {
+    let mut out = Vec::new();
+    inp.by_ref().take(len).read_to_end(&mut out)?;
+    if (out.len() as u64) < len {
+        return Err(Error::Truncated);
+    }
+    Ok(out)
+}
+
+/// Fixed size part of an index file
+struct Header {
+    kind: IndexKind,
+    meta_len: u64,
+    payload_len: u64,
+    checksum: u32,
+}
+
+fn read_header<R: Read>(inp: &mut R) -> Result<Header, Error> {
+    let mut buf = [0u8; HEADER_LEN];
+    inp.read_exact(&mut buf)?;
+
+    if &buf[0..4] != MAGIC {
+        return Err(Error::InvalidMagic);
+    }
+
+    let read_u32 = |pos: usize| u32::from_le_bytes(buf[pos..pos + 4].try_into().unwrap());
+
+    let version = read_u32(4);
+    if version != FORMAT_VERSION {
+        return Err(Error::UnsupportedVersion(version));
+    }
+
+    let kind = read_u32(8);
+    let kind = IndexKind::from_u32(kind).ok_or(Error::UnknownKind(kind))?;
+
+    Ok(Header {
+        kind,
+        meta_len: read_u32(12) as u64,
+        payload_len: u64::from_le_bytes(buf[16..24].try_into().unwrap()),
+        checksum: read_u32(24),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Terms(Vec<String>);
+
+    impl IndexFile for Terms {
+        const KIND: IndexKind = IndexKind::Basic;
+
+        fn term_count(&self) -> usize {
+            self.0.len()
+        }
+    }
+
+    #[derive(Serialize, Deserialize, Debug)]
+    struct Readings(Vec<String>);
+
+    impl IndexFile for Readings {
+        const KIND: IndexKind = IndexKind::Japanese;
+
+        fn term_count(&self) -> usize {
+            self.0.len()
+        }
+    }
+
+    fn terms() -> Terms {
+        Terms(vec!["hello".to_string(), "world".to_string()])
+    }
+
+    fn saved() -> Vec<u8> {
+        let mut out = vec![];
+        terms().save(&mut out).unwrap();
+        out
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let file = saved();
+        assert_eq!(Terms::load(file.as_slice()).unwrap(), terms());
+
+        let meta = read_meta(file.as_slice()).unwrap();
+        assert_eq!(meta.kind, IndexKind::Basic);
+        assert_eq!(meta.format_version, FORMAT_VERSION);
+        assert_eq!(meta.term_count, 2);
+
+        let (loaded_meta, loaded) = Terms::load_with_meta(file.as_slice()).unwrap();
+        assert_eq!(loaded_meta, meta);
+        assert_eq!(loaded, terms());
+    }
+
+    #[test]
+    fn test_invalid_magic() {
+        let mut file = saved();
+        file[0] = b'X';
+        assert!(matches!(
+            Terms::load(file.as_slice()),
+            Err(Error::InvalidMagic)
+        ));
+    }
+
+    #[test]
+    fn test_kind_mismatch() {
+        let file = saved();
+        assert!(matches!(
+            Readings::load(file.as_slice()),
+            Err(Error::KindMismatch {
+                expected: IndexKind::Japanese,
+                found: IndexKind::Basic,
+            })
+        ));
+
+        let mut file = saved();
+        file[8..12].copy_from_slice(&99u32.to_le_bytes());
+        assert!(matches!(
+            Terms::load(file.as_slice()),
+            Err(Error::UnknownKind(99))
+        ));
+    }
+
+    #[test]
+    fn test_future_version() {
+        let mut file = saved();
+        file[4..8].copy_from_slice(&(FORMAT_VERSION + 1).to_le_bytes());
+        assert!(matches!(
+            Terms::load(file.as_slice()),
+            Err(Error::UnsupportedVersion(v)) if v == FORMAT_VERSION + 1
+        ));
+    }
+
+    #[test]
+    fn test_checksum_mismatch() {
+        let mut file = saved();
+        let last = file.len() - 1;
+        file[last] ^= 0xff;
+        assert!(matches!(
+            Terms::load(file.as_slice()),
+            Err(Error::ChecksumMismatch)
+        ));
+    }
+
+    #[test]
+    fn test_truncated() {
+        let mut file = saved();
+        file.pop();
+        assert!(matches!(
+            Terms::load(file.as_slice()),
+            Err(Error::Truncated)
+        ));
+    }
+}
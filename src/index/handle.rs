@@ -0,0 +1,282 @@
+use std::{
+    fs::File,
+    io::BufReader,
+    ops::Deref,
+    path::Path,
+    sync::{Arc, PoisonError, RwLock},
+};
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    file::{self, IndexFile},
+    Output, SuggestionIndex,
+};
+use crate::suggest::{result::SuggestionResult, task::SuggestionTask};
+
+/// Cheaply cloneable handle owning an index. Can be shared across threads and hands out
+/// owned results which don't borrow from the index.
+pub struct IndexHandle<I: ?Sized> {
+    index: Arc<I>,
+}
+
+impl<I: SuggestionIndex> IndexHandle<I> {
+    /// Create a new IndexHandle owning `index`
+    #[inline]
+    pub fn new(index: I) -> Self {
+        Self {
+            index: Arc::new(index),
+        }
+    }
+}
+
+impl<I: SuggestionIndex + ?Sized> IndexHandle<I> {
+    /// Create a new IndexHandle from an already shared index
+    #[inline]
+    pub fn from_arc(index: Arc<I>) -> Self {
+        Self { index }
+    }
+
+    /// Returns the shared index
+    #[inline]
+    pub fn index(&self) -> &Arc<I> {
+        &self.index
+    }
+
+    /// Builds a task on the index with `build` and runs it
+    pub fn search<F>(&self, build: F) -> Vec<SuggestionResult>
+    where
+        F: FnOnce(&I) -> SuggestionTask<'_, '_, '_>,
+    {
+        build(&self.index).search_results()
+    }
+
+    /// Returns the output of the word with the given word ID, eg. of a cached result
+    #[inline]
+    pub fn output(&self, word_id: u32) -> Option<Output> {
+        Some(self.index.word_by_id(word_id)?.to_output())
+    }
+}
+
+impl<I: ?Sized> Clone for IndexHandle<I> {
+    #[inline]
+    fn clone(&self) -> Self {
+        Self {
+            index: Arc::clone(&self.index),
+        }
+    }
+}
+
+impl<I: ?Sized> Deref for IndexHandle<I> {
+    type Target = I;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.index
+    }
+}
+
+impl<I: SuggestionIndex> From<I> for IndexHandle<I> {
+    #[inline]
+    fn from(index: I) -> Self {
+        Self::new(index)
+    }
+}
+
+/// Version of the index that is active in a `SwapHandle`
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct IndexVersion {
+    /// Increased on every swap, starting at 0
+    pub generation: u64,
+    /// User defined label, eg. the build date of the index
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+}
+
+/// Results of a search together with the version of the index that produced them
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SearchResponse {
+    pub version: IndexVersion,
+    pub results: Vec<SuggestionResult>,
+}
+
+/// An index of a `SwapHandle` at a single point in time. Keeps the index alive, even if it
+/// got swapped out in the meantime
+pub struct Snapshot<I> {
+    handle: IndexHandle<I>,
+    version: IndexVersion,
+}
+
+impl<I: SuggestionIndex> Snapshot<I> {
+    /// Returns the version of the index
+    #[inline]
+    pub fn version(&self) -> &IndexVersion {
+        &self.version
+    }
+
+    /// Returns the handle of the index
+    #[inline]
+    pub fn handle(&self) -> &IndexHandle<I> {
+        &self.handle
+    }
+
+    /// Builds a task on the index with `build` and runs it
+    pub fn search<F>(&self, build: F) -> SearchResponse
+    where
+        F: FnOnce(&I) -> SuggestionTask<'_, '_, '_>,
+    {
+        SearchResponse {
+            version: self.version.clone(),
+            results: self.handle.search(build),
+        }
+    }
+}
+
+impl<I> Clone for Snapshot<I> {
+    #[inline]
+    fn clone(&self) -> Self {
+        Self {
+            handle: self.handle.clone(),
+            version: self.version.clone(),
+        }
+    }
+}
+
+impl<I> Deref for Snapshot<I> {
+    type Target = I;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.handle
+    }
+}
+
+/// Handle to an index that can be replaced while it's in use. Each search runs on a
+/// `Snapshot` of the index that was active when it started, so in-flight searches finish on
+/// the old index which gets dropped once its last snapshot is gone.
+pub struct SwapHandle<I> {
+    active: Arc<RwLock<Snapshot<I>>>,
+}
+
+impl<I: SuggestionIndex> SwapHandle<I> {
+    /// Create a new SwapHandle with `index` as first version
+    pub fn new(index: I, label: Option<String>) -> Self {
+        let snapshot = Snapshot {
+            handle: IndexHandle::new(index),
+            version: IndexVersion {
+                generation: 0,
+                label,
+            },
+        };
+        Self {
+            active: Arc::new(RwLock::new(snapshot)),
+        }
+    }
+
+    /// Returns the currently active index
+    #[inline]
+    pub fn snapshot(&self) -> Snapshot<I> {
+        self.active
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Returns the version of the currently active index
+    #[inline]
+    pub fn version(&self) -> IndexVersion {
+        self.snapshot().version
+    }
+
+    /// Replaces the active index with `index` and returns its version. Searches that already
+    /// started keep using the old index
+    pub fn swap(&self, index: I, label: Option<String>) -> IndexVersion {
+        let handle = IndexHandle::new(index);
+        let mut active = self.active.write().unwrap_or_else(PoisonError::into_inner);
+        let version = IndexVersion {
+            generation: active.version.generation + 1,
+            label,
+        };
+        let old = std::mem::replace(
+            &mut *active,
+            Snapshot {
+                handle,
+                version: version.clone(),
+            },
+        );
+        drop(active);
+
+        // Drop the old index (if it's not used anymore) without blocking readers
+        drop(old);
+        version
+    }
+
+    /// Builds a task on the currently active index with `build` and runs it
+    #[inline]
+    pub fn search<F>(&self, build: F) -> SearchResponse
+    where
+        F: FnOnce(&I) -> SuggestionTask<'_, '_, '_>,
+    {
+        self.snapshot().search(build)
+    }
+}
+
+impl<I: SuggestionIndex + IndexFile> SwapHandle<I> {
+    /// Loads the index file at `path` and swaps it in. The creation time of the file is used
+    /// as label. The active index stays unchanged if loading fails
+    pub fn swap_file<P: AsRef<Path>>(&self, path: P) -> Result<IndexVersion, file::Error> {
+        let (meta, index) = I::load_with_meta(BufReader::new(File::open(path)?))?;
+        Ok(self.swap(index, Some(meta.created.to_string())))
+    }
+}
+
+impl<I> Clone for SwapHandle<I> {
+    #[inline]
+    fn clone(&self) -> Self {
+        Self {
+            active: Arc::clone(&self.active),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::index::{
+        basic::{builder::BasicIndexBuilder, Item},
+        test_util,
+    };
+
+    #[test]
+    fn test_output_by_word_id() {
+        let mut builder = BasicIndexBuilder::new();
+        for (word, id) in [("apple", 20), ("banana", 10)] {
+            assert!(builder
+                .insert(Item::new(word.to_string(), id, 0.5), word)
+                .is_ok());
+        }
+        let handle = IndexHandle::new(builder.build());
+
+        assert_eq!(handle.output(20).unwrap().primary, "apple");
+        assert_eq!(handle.output(10).unwrap().primary, "banana");
+        assert!(handle.output(0).is_none());
+        assert!(handle.output(1).is_none());
+    }
+
+    #[test]
+    fn test_swap_file() {
+        let index = test_util::basic_index(&[("apple", 0.5)], |_| ());
+        let path = std::env::temp_dir().join(format!("swap_file_{}.idx", std::process::id()));
+        index.save_file(&path).unwrap();
+        let meta = file::read_meta(BufReader::new(File::open(&path).unwrap())).unwrap();
+
+        let handle = SwapHandle::new(test_util::basic_index(&[("banana", 0.4)], |_| ()), None);
+        let version = handle.swap_file(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        let version = version.unwrap();
+        assert_eq!(version.generation, 1);
+        assert_eq!(version.label, Some(meta.created.to_string()));
+        assert_eq!(handle.snapshot().exact("apple").len(), 1);
+    }
+}
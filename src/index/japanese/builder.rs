@@ -1,4 +1,8 @@
 use super::{Item, JapaneseIndex};
+use crate::{
+    index::{prefix_tree::PrefixTreeBuilder, WordIdMap},
+    normalize::{kana, Normalizer, NormalizerPipeline},
+};
 use ngindex::{builder::NGIndexBuilder, NGIndex};
 use qp_trie::{wrapper::BString, Trie};
 use std::collections::{HashMap, HashSet};
@@ -9,9 +13,10 @@ pub struct JpIndexBulider {
     n: usize,
     ng_map: HashMap<String, HashSet<u32>>,
 
-    trie: Trie<BString, Vec<u32>>,
+    tree: PrefixTreeBuilder,
     items: Vec<Item>,
     kanji_align: Trie<BString, Vec<u32>>,
+    normalizer: NormalizerPipeline,
 }
 
 impl JpIndexBulider {
@@ -21,9 +26,10 @@ impl JpIndexBulider {
             n: 0,
             ng_map: HashMap::new(),
 
-            trie: Trie::new(),
+            tree: PrefixTreeBuilder::new(),
             items: vec![],
             kanji_align: Trie::new(),
+            normalizer: NormalizerPipeline::default(),
         }
     }
 
@@ -33,26 +39,33 @@ impl JpIndexBulider {
             n,
             ng_map: HashMap::new(),
 
-            trie: Trie::new(),
+            tree: PrefixTreeBuilder::new(),
             items: vec![],
             kanji_align: Trie::new(),
+            normalizer: NormalizerPipeline::default(),
         }
     }
 
+    /// Sets the normalizer that gets applied to all inserted readings. The index keeps it to
+    /// normalize queries the same way
+    pub fn set_normalizer(&mut self, normalizer: NormalizerPipeline) {
+        self.normalizer = normalizer;
+    }
+
     /// Adds an item to the new index. Uses the items readings as keys.
     /// Does not index kanji reading align and normal_kana since `item` doesn't hold that data
     /// Returns the id of the new item
     pub fn add_item(&mut self, item: Item) -> u32 {
         let id = self.items.len() as u32;
 
-        self.insert_trie(&item.kana, id);
+        self.insert_key(&item.kana, id);
 
         if let Some(ref kanji) = item.kanji {
-            self.insert_trie(kanji, id);
+            self.insert_key(kanji, id);
         }
 
         for alt in &item.alternative {
-            self.insert_trie(alt, id);
+            self.insert_key(alt, id);
         }
 
         self.items.push(item);
@@ -66,7 +79,7 @@ impl JpIndexBulider {
         self.items.push(item);
 
         for reading in readings {
-            self.insert_trie(reading.as_ref(), id);
+            self.insert_key(reading.as_ref(), id);
         }
 
         id
@@ -75,7 +88,7 @@ impl JpIndexBulider {
     /// Adds readings that'll map to the item with the given ID
     pub fn add_readings<S: AsRef<str>>(&mut self, readings: &[S], id: u32) {
         for reading in readings {
-            self.insert_trie(reading.as_ref(), id);
+            self.insert_key(reading.as_ref(), id);
         }
     }
 
@@ -86,17 +99,58 @@ impl JpIndexBulider {
         }
 
         for reading in readings.iter().map(|i| i.as_ref()) {
-            self.ng_map
-                .entry(reading.to_string())
-                .or_default()
-                .insert(id);
+            let reading = self.normalizer.normalize(reading);
+            let unified = kana::unify(&reading);
+            if unified != reading {
+                self.ng_map.entry(unified).or_default().insert(id);
+            }
+            self.ng_map.entry(reading).or_default().insert(id);
         }
     }
 
     pub fn insert_kalign<S: AsRef<str>>(&mut self, readings: &[S], id: u32) {
         for reading in readings {
-            insert_or_update(&mut self.kanji_align, reading.as_ref(), id);
+            let reading = self.normalizer.normalize(reading.as_ref());
+            let unified = kana::unify(&reading);
+            if unified != reading {
+                insert_or_update(&mut self.kanji_align, &unified, id);
+            }
+            insert_or_update(&mut self.kanji_align, &reading, id);
+        }
+    }
+
+    /// Inserts all mixed kanji/kana spellings of an aligned word, so partially converted input
+    /// like "たべ物" or "日ほん" finds it. `parts` holds the surface and reading of each part of
+    /// the word, eg. [("日", "に"), ("本", "ほん"), ("語", "ご")]. Kana parts have the same
+    /// surface and reading. Words with more than `MAX_ALIGN_PARTS` kanji parts only get their
+    /// surface and full reading inserted. The alignment is also used as furigana of the item if
+    /// it doesn't have any yet
+    pub fn insert_aligned<S: AsRef<str>>(&mut self, parts: &[(S, S)], id: u32) {
+        let parts: Vec<(&str, &str)> = parts
+            .iter()
+            .map(|(s, r)| (s.as_ref(), r.as_ref()))
+            .collect();
+
+        if let Some(item) = self.items.get_mut(id as usize) {
+            if item.furigana.is_none() && item.kanji.is_some() {
+                let furigana = parts
+                    .iter()
+                    .map(|(s, r)| (s.to_string(), r.to_string()))
+                    .collect();
+                item.furigana = Some(furigana);
+            }
         }
+        let kanji_parts = parts.iter().filter(|(s, r)| s != r).count();
+
+        let spellings: Vec<String> = if kanji_parts <= MAX_ALIGN_PARTS {
+            (0..1u32 << kanji_parts)
+                .map(|mask| mixed_spelling(&parts, mask))
+                .collect()
+        } else {
+            vec![mixed_spelling(&parts, 0), mixed_spelling(&parts, u32::MAX)]
+        };
+
+        self.insert_kalign(&spellings, id);
     }
 
     /// Create a JapaneseIndex out of the builder
@@ -106,11 +160,16 @@ impl JpIndexBulider {
             ngindex = Self::build_ngindex(self.n, self.ng_map);
         }
 
+        let items = self.items;
+        let tree = self.tree.build(|id| items[id as usize].frequency);
+
         JapaneseIndex {
-            trie: self.trie,
-            items: self.items,
+            tree,
+            items,
             kanji_align: self.kanji_align,
             ngindex,
+            normalizer: self.normalizer,
+            word_ids: WordIdMap::default(),
         }
     }
 
@@ -123,14 +182,46 @@ impl JpIndexBulider {
         builder.build()
     }
 
-    fn insert_trie(&mut self, reading: &str, id: u32) {
-        insert_or_update(&mut self.trie, reading, id);
+    /// Inserts the normalized `reading` and its kana unified form, which is used to match
+    /// queries regardless of their script
+    fn insert_key(&mut self, reading: &str, id: u32) {
+        let reading = self.normalizer.normalize(reading);
+        let unified = kana::unify(&reading);
+        if unified != reading {
+            self.tree.insert(&unified, id);
+        }
+        self.tree.insert(&reading, id);
     }
 }
 
+/// Max amount of kanji parts of an aligned word whose mixed spellings get all indexed
+const MAX_ALIGN_PARTS: usize = 6;
+
+/// Builds a spelling of the aligned `parts` which uses the reading of each kanji part with its
+/// bit set in `mask`, and the surface of all others
+fn mixed_spelling(parts: &[(&str, &str)], mask: u32) -> String {
+    let mut out = String::new();
+    let mut kanji_pos = 0;
+    for (surface, reading) in parts {
+        if surface == reading {
+            out.push_str(surface);
+            continue;
+        }
+        if (mask >> kanji_pos.min(31)) & 1 == 1 {
+            out.push_str(reading);
+        } else {
+            out.push_str(surface);
+        }
+        kanji_pos += 1;
+    }
+    out
+}
+
 fn insert_or_update(trie: &mut Trie<BString, Vec<u32>>, item: &str, id: u32) {
     if let Some(v) = trie.get_mut_str(item) {
-        v.push(id);
+        if !v.contains(&id) {
+            v.push(id);
+        }
     } else {
         trie.insert_str(item, vec![id]);
     }
@@ -0,0 +1,266 @@
+use super::JapaneseIndex;
+use crate::{
+    index::{prefix_tree::TreeView, Output, ToOutput},
+    normalize::{kana, Normalizer},
+};
+
+/// Max length of a dictionary word in characters
+const MAX_WORD_LEN: usize = 16;
+
+/// Constant cost of each segment. Makes paths with fewer, longer words cheaper
+const WORD_COST: f64 = 2.0;
+
+/// Cost of a single character that is not part of any dictionary word
+const UNKNOWN_COST: f64 = 12.0;
+
+/// Lowest frequency used to calculate word costs
+const MIN_FREQ: f64 = 1e-6;
+
+/// Highest cost of a dictionary word. Words without frequency data would cost more than an
+/// unknown character otherwise, so even single kana particles would never be used
+const MAX_WORD_COST: f64 = UNKNOWN_COST - 1.0;
+
+/// A single word of a conversion
+#[derive(Clone, Debug, PartialEq)]
+pub struct Segment {
+    /// Part of the input this segment covers
+    pub reading: String,
+    /// Kanji (or kana) representation of `reading`
+    pub surface: String,
+    /// ID of the dictionary word. `None` for characters that aren't known to the index
+    pub word_id: Option<u32>,
+}
+
+/// A segmentation of the input into dictionary words
+#[derive(Clone, Debug, PartialEq)]
+pub struct Conversion {
+    pub segments: Vec<Segment>,
+    /// Total cost of the path. Lower is better
+    pub cost: f64,
+}
+
+impl Conversion {
+    /// Returns the converted text
+    pub fn surface(&self) -> String {
+        self.segments.iter().map(|i| i.surface.as_str()).collect()
+    }
+
+    /// Returns the reading of the converted text
+    pub fn reading(&self) -> String {
+        self.segments.iter().map(|i| i.reading.as_str()).collect()
+    }
+}
+
+impl ToOutput for Conversion {
+    #[inline]
+    fn to_output(&self) -> Output {
+        let mut output = Output::new(self.reading(), Some(self.surface()));
+        output.furigana = Some(
+            self.segments
+                .iter()
+                .map(|i| (i.surface.clone(), i.reading.clone()))
+                .collect(),
+        );
+        output
+    }
+}
+
+/// An edge of the lattice, ending at the `end`th character of the input
+struct Edge {
+    end: usize,
+    cost: f64,
+    segment: Segment,
+}
+
+/// Best path ending at a given position
+#[derive(Clone)]
+struct Path {
+    cost: f64,
+    /// Start position and index of each edge
+    edges: Vec<(usize, usize)>,
+}
+
+impl JapaneseIndex {
+    /// Converts a kana phrase into the `limit` best segmentations of dictionary words, using
+    /// the item frequencies as costs ("にほんごをべんきょうする" -> 日本語を勉強する).
+    /// Characters that are not part of any word are kept as they are.
+    pub fn convert(&self, inp: &str, limit: usize) -> Vec<Conversion> {
+        let inp = kana::unify(&self.normalizer.normalize(inp));
+        let chars: Vec<char> = inp.chars().collect();
+        if chars.is_empty() || limit == 0 {
+            return vec![];
+        }
+
+        let edges = self.build_lattice(&chars);
+
+        // N-best viterbi: the `limit` cheapest paths ending at each position
+        let mut paths: Vec<Vec<Path>> = vec![vec![]; chars.len() + 1];
+        paths[0].push(Path {
+            cost: 0.0,
+            edges: vec![],
+        });
+
+        for (pos, pos_edges) in edges.iter().enumerate() {
+            if paths[pos].is_empty() {
+                continue;
+            }
+
+            for (edge_id, edge) in pos_edges.iter().enumerate() {
+                let new_paths: Vec<_> = paths[pos]
+                    .iter()
+                    .map(|path| {
+                        let mut edges = path.edges.clone();
+                        edges.push((pos, edge_id));
+                        Path {
+                            cost: path.cost + edge.cost,
+                            edges,
+                        }
+                    })
+                    .collect();
+
+                let target = &mut paths[edge.end];
+                target.extend(new_paths);
+                target.sort_by(|a, b| a.cost.total_cmp(&b.cost));
+                target.truncate(limit);
+            }
+        }
+
+        let mut out: Vec<Conversion> = Vec::with_capacity(limit);
+        for path in paths.pop().unwrap_or_default() {
+            let conversion = Conversion {
+                segments: path
+                    .edges
+                    .iter()
+                    .map(|(pos, i)| edges[*pos][*i].segment.clone())
+                    .collect(),
+                cost: path.cost,
+            };
+
+            // Different words can have the same surface
+            let surface = conversion.surface();
+            if out.iter().any(|i| i.surface() == surface) {
+                continue;
+            }
+            out.push(conversion);
+        }
+
+        out
+    }
+
+    /// Returns all words that occur in `chars`, grouped by their start position. Each
+    /// character additionally gets its own edge so every position can be reached
+    fn build_lattice(&self, chars: &[char]) -> Vec<Vec<Edge>> {
+        let mut edges: Vec<Vec<Edge>> = Vec::with_capacity(chars.len());
+        let tree = self.tree.view();
+
+        for (start, c) in chars.iter().enumerate() {
+            let mut start_edges = vec![Edge {
+                end: start + 1,
+                cost: UNKNOWN_COST,
+                segment: Segment {
+                    reading: c.to_string(),
+                    surface: c.to_string(),
+                    word_id: None,
+                },
+            }];
+
+            // Walk the tree along the input, so each node holds the words of one reading
+            let max_end = (start + MAX_WORD_LEN).min(chars.len());
+            let mut node = TreeView::ROOT;
+            for end in start + 1..=max_end {
+                node = match tree.child(node, chars[end - 1]) {
+                    Some(child) => child,
+                    None => break,
+                };
+                let ids = tree.node_ids(node);
+                if ids.is_empty() {
+                    continue;
+                }
+                let reading: String = chars[start..end].iter().collect();
+
+                for item in ids.iter().map(|i| self.get_item(*i)) {
+                    let surface = item.kanji.clone().unwrap_or_else(|| item.kana.clone());
+                    start_edges.push(Edge {
+                        end,
+                        cost: word_cost(item.frequency),
+                        segment: Segment {
+                            reading: reading.clone(),
+                            surface,
+                            word_id: Some(item.word_id),
+                        },
+                    });
+                }
+            }
+
+            edges.push(start_edges);
+        }
+
+        edges
+    }
+}
+
+/// Returns the cost of a dictionary word with the given frequency
+#[inline]
+fn word_cost(frequency: f64) -> f64 {
+    (WORD_COST - frequency.max(MIN_FREQ).ln()).min(MAX_WORD_COST)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::index::test_util;
+
+    fn build() -> JapaneseIndex {
+        test_util::japanese_index(&[
+            ("にほん", Some("日本"), 0.01),
+            ("にほんご", Some("日本語"), 0.005),
+            ("ご", Some("語"), 0.001),
+            ("を", None, 0.05),
+            ("べんきょう", Some("勉強"), 0.005),
+            ("する", None, 0.05),
+            ("は", None, 0.0),
+        ])
+    }
+
+    #[test]
+    fn test_convert_sentence() {
+        let index = build();
+        let out = index.convert("にほんごをべんきょうする", 1);
+        assert_eq!(out.len(), 1);
+        assert_eq!(out[0].surface(), "日本語を勉強する");
+        assert_eq!(out[0].reading(), "にほんごをべんきょうする");
+        let ids: Vec<_> = out[0].segments.iter().map(|i| i.word_id).collect();
+        assert_eq!(ids, vec![Some(1), Some(3), Some(4), Some(5)]);
+    }
+
+    #[test]
+    fn test_n_best_ordered() {
+        let index = build();
+        let out = index.convert("にほんご", 3);
+        assert_eq!(out[0].surface(), "日本語");
+        assert!(out.len() > 1);
+        for pair in out.windows(2) {
+            assert!(pair[0].cost <= pair[1].cost);
+            assert_ne!(pair[0].surface(), pair[1].surface());
+        }
+    }
+
+    #[test]
+    fn test_unknown_characters() {
+        let index = build();
+        let out = index.convert("にほんx", 1);
+        assert_eq!(out[0].surface(), "日本x");
+        assert_eq!(out[0].segments.last().unwrap().word_id, None);
+        assert!(index.convert("", 1).is_empty());
+    }
+
+    #[test]
+    fn test_word_without_frequency() {
+        assert!(word_cost(0.0) < UNKNOWN_COST);
+        assert!(word_cost(0.5) < word_cost(0.01));
+
+        let index = build();
+        let out = index.convert("は", 1);
+        assert_eq!(out[0].segments[0].word_id, Some(6));
+    }
+}
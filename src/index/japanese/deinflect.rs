@@ -0,0 +1,423 @@
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+
+/// A conjugation that got removed from a word
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Inflection {
+    Negative,
+    Past,
+    Polite,
+    Te,
+    Progressive,
+    Desire,
+    Potential,
+    Passive,
+    Causative,
+    Volitional,
+    Imperative,
+    Conditional,
+    Adverbial,
+}
+
+impl Inflection {
+    const ALL: [Inflection; 13] = [
+        Inflection::Negative,
+        Inflection::Past,
+        Inflection::Polite,
+        Inflection::Te,
+        Inflection::Progressive,
+        Inflection::Desire,
+        Inflection::Potential,
+        Inflection::Passive,
+        Inflection::Causative,
+        Inflection::Volitional,
+        Inflection::Imperative,
+        Inflection::Conditional,
+        Inflection::Adverbial,
+    ];
+
+    #[inline]
+    fn bit(self) -> u16 {
+        1 << self as u16
+    }
+}
+
+/// Set of inflections
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(into = "Vec<Inflection>", from = "Vec<Inflection>")]
+pub struct Inflections(u16);
+
+impl Inflections {
+    /// Returns a new set with `inflection` added
+    #[inline]
+    pub fn with(self, inflection: Inflection) -> Self {
+        Self(self.0 | inflection.bit())
+    }
+
+    #[inline]
+    pub fn contains(&self, inflection: Inflection) -> bool {
+        self.0 & inflection.bit() != 0
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// Returns all inflections of the set
+    pub fn iter(&self) -> impl Iterator<Item = Inflection> + '_ {
+        Inflection::ALL.into_iter().filter(|i| self.contains(*i))
+    }
+}
+
+impl From<Inflections> for Vec<Inflection> {
+    #[inline]
+    fn from(inflections: Inflections) -> Self {
+        inflections.iter().collect()
+    }
+}
+
+impl From<Vec<Inflection>> for Inflections {
+    #[inline]
+    fn from(inflections: Vec<Inflection>) -> Self {
+        inflections
+            .into_iter()
+            .fold(Inflections::default(), |s, i| s.with(i))
+    }
+}
+
+/// Word types used to chain rules. A rule only applies to a word that has one of the rules
+/// input types. The input itself has all types
+const V1: u8 = 1;
+const V5: u8 = 1 << 1;
+const VK: u8 = 1 << 2;
+const VS: u8 = 1 << 3;
+const ADJ_I: u8 = 1 << 4;
+/// Polite ます form
+const MASU: u8 = 1 << 5;
+/// て form
+const TE: u8 = 1 << 6;
+/// た form
+const TA: u8 = 1 << 7;
+const ANY: u8 = u8::MAX;
+
+/// A single deinflection rule
+struct Rule {
+    /// Inflected ending
+    from: &'static str,
+    /// Ending of the deinflected word
+    to: &'static str,
+    /// Types of the inflected word
+    type_in: u8,
+    /// Type of the deinflected word
+    type_out: u8,
+    inflection: Inflection,
+}
+
+const fn rule(
+    from: &'static str,
+    to: &'static str,
+    type_in: u8,
+    type_out: u8,
+    inflection: Inflection,
+) -> Rule {
+    Rule {
+        from,
+        to,
+        type_in,
+        type_out,
+        inflection,
+    }
+}
+
+use Inflection::*;
+
+/// Conjugations of verbs and i-adjectives
+#[rustfmt::skip]
+static RULES: &[Rule] = &[
+    // Negative
+    rule("ない", "る", ADJ_I, V1, Negative),
+    rule("わない", "う", ADJ_I, V5, Negative),
+    rule("かない", "く", ADJ_I, V5, Negative),
+    rule("がない", "ぐ", ADJ_I, V5, Negative),
+    rule("さない", "す", ADJ_I, V5, Negative),
+    rule("たない", "つ", ADJ_I, V5, Negative),
+    rule("なない", "ぬ", ADJ_I, V5, Negative),
+    rule("ばない", "ぶ", ADJ_I, V5, Negative),
+    rule("まない", "む", ADJ_I, V5, Negative),
+    rule("らない", "る", ADJ_I, V5, Negative),
+    rule("こない", "くる", ADJ_I, VK, Negative),
+    rule("しない", "する", ADJ_I, VS, Negative),
+    rule("くない", "い", ADJ_I, ADJ_I, Negative),
+    rule("ません", "ます", ANY, MASU, Negative),
+    // Past
+    rule("た", "る", ANY, V1, Past),
+    rule("った", "う", ANY, V5, Past),
+    rule("った", "つ", ANY, V5, Past),
+    rule("った", "る", ANY, V5, Past),
+    rule("いた", "く", ANY, V5, Past),
+    rule("いだ", "ぐ", ANY, V5, Past),
+    rule("した", "す", ANY, V5, Past),
+    rule("んだ", "ぬ", ANY, V5, Past),
+    rule("んだ", "ぶ", ANY, V5, Past),
+    rule("んだ", "む", ANY, V5, Past),
+    rule("いった", "いく", ANY, V5, Past),
+    rule("行った", "行く", ANY, V5, Past),
+    rule("きた", "くる", ANY, VK, Past),
+    rule("来た", "来る", ANY, VK, Past),
+    rule("した", "する", ANY, VS, Past),
+    rule("かった", "い", ANY, ADJ_I, Past),
+    rule("ました", "ます", ANY, MASU, Past),
+    rule("ませんでした", "ません", ANY, MASU, Past),
+    rule("たら", "た", ANY, TA, Conditional),
+    rule("だら", "だ", ANY, TA, Conditional),
+    // て form
+    rule("て", "る", TE, V1, Te),
+    rule("って", "う", TE, V5, Te),
+    rule("って", "つ", TE, V5, Te),
+    rule("って", "る", TE, V5, Te),
+    rule("いて", "く", TE, V5, Te),
+    rule("いで", "ぐ", TE, V5, Te),
+    rule("して", "す", TE, V5, Te),
+    rule("んで", "ぬ", TE, V5, Te),
+    rule("んで", "ぶ", TE, V5, Te),
+    rule("んで", "む", TE, V5, Te),
+    rule("いって", "いく", TE, V5, Te),
+    rule("行って", "行く", TE, V5, Te),
+    rule("きて", "くる", TE, VK, Te),
+    rule("来て", "来る", TE, VK, Te),
+    rule("して", "する", TE, VS, Te),
+    rule("くて", "い", TE, ADJ_I, Te),
+    rule("ている", "て", V1, TE, Progressive),
+    rule("でいる", "で", V1, TE, Progressive),
+    rule("てる", "て", V1, TE, Progressive),
+    rule("でる", "で", V1, TE, Progressive),
+    // Polite
+    rule("ます", "る", MASU, V1, Polite),
+    rule("います", "う", MASU, V5, Polite),
+    rule("きます", "く", MASU, V5, Polite),
+    rule("ぎます", "ぐ", MASU, V5, Polite),
+    rule("します", "す", MASU, V5, Polite),
+    rule("ちます", "つ", MASU, V5, Polite),
+    rule("にます", "ぬ", MASU, V5, Polite),
+    rule("びます", "ぶ", MASU, V5, Polite),
+    rule("みます", "む", MASU, V5, Polite),
+    rule("ります", "る", MASU, V5, Polite),
+    rule("きます", "くる", MASU, VK, Polite),
+    rule("します", "する", MASU, VS, Polite),
+    rule("ましょう", "ます", ANY, MASU, Volitional),
+    // Desire
+    rule("たい", "る", ADJ_I, V1, Desire),
+    rule("いたい", "う", ADJ_I, V5, Desire),
+    rule("きたい", "く", ADJ_I, V5, Desire),
+    rule("ぎたい", "ぐ", ADJ_I, V5, Desire),
+    rule("したい", "す", ADJ_I, V5, Desire),
+    rule("ちたい", "つ", ADJ_I, V5, Desire),
+    rule("にたい", "ぬ", ADJ_I, V5, Desire),
+    rule("びたい", "ぶ", ADJ_I, V5, Desire),
+    rule("みたい", "む", ADJ_I, V5, Desire),
+    rule("りたい", "る", ADJ_I, V5, Desire),
+    rule("きたい", "くる", ADJ_I, VK, Desire),
+    rule("したい", "する", ADJ_I, VS, Desire),
+    // Potential
+    rule("られる", "る", V1, V1, Potential),
+    rule("える", "う", V1, V5, Potential),
+    rule("ける", "く", V1, V5, Potential),
+    rule("げる", "ぐ", V1, V5, Potential),
+    rule("せる", "す", V1, V5, Potential),
+    rule("てる", "つ", V1, V5, Potential),
+    rule("ねる", "ぬ", V1, V5, Potential),
+    rule("べる", "ぶ", V1, V5, Potential),
+    rule("める", "む", V1, V5, Potential),
+    rule("れる", "る", V1, V5, Potential),
+    rule("こられる", "くる", V1, VK, Potential),
+    rule("できる", "する", V1, VS, Potential),
+    // Passive
+    rule("られる", "る", V1, V1, Passive),
+    rule("われる", "う", V1, V5, Passive),
+    rule("かれる", "く", V1, V5, Passive),
+    rule("がれる", "ぐ", V1, V5, Passive),
+    rule("される", "す", V1, V5, Passive),
+    rule("たれる", "つ", V1, V5, Passive),
+    rule("なれる", "ぬ", V1, V5, Passive),
+    rule("ばれる", "ぶ", V1, V5, Passive),
+    rule("まれる", "む", V1, V5, Passive),
+    rule("られる", "る", V1, V5, Passive),
+    rule("される", "する", V1, VS, Passive),
+    // Causative
+    rule("させる", "る", V1, V1, Causative),
+    rule("わせる", "う", V1, V5, Causative),
+    rule("かせる", "く", V1, V5, Causative),
+    rule("がせる", "ぐ", V1, V5, Causative),
+    rule("させる", "す", V1, V5, Causative),
+    rule("たせる", "つ", V1, V5, Causative),
+    rule("なせる", "ぬ", V1, V5, Causative),
+    rule("ばせる", "ぶ", V1, V5, Causative),
+    rule("ませる", "む", V1, V5, Causative),
+    rule("らせる", "る", V1, V5, Causative),
+    rule("こさせる", "くる", V1, VK, Causative),
+    rule("させる", "する", V1, VS, Causative),
+    // Volitional
+    rule("よう", "る", ANY, V1, Volitional),
+    rule("おう", "う", ANY, V5, Volitional),
+    rule("こう", "く", ANY, V5, Volitional),
+    rule("ごう", "ぐ", ANY, V5, Volitional),
+    rule("そう", "す", ANY, V5, Volitional),
+    rule("とう", "つ", ANY, V5, Volitional),
+    rule("のう", "ぬ", ANY, V5, Volitional),
+    rule("ぼう", "ぶ", ANY, V5, Volitional),
+    rule("もう", "む", ANY, V5, Volitional),
+    rule("ろう", "る", ANY, V5, Volitional),
+    rule("こよう", "くる", ANY, VK, Volitional),
+    rule("しよう", "する", ANY, VS, Volitional),
+    // Imperative
+    rule("ろ", "る", ANY, V1, Imperative),
+    rule("え", "う", ANY, V5, Imperative),
+    rule("け", "く", ANY, V5, Imperative),
+    rule("げ", "ぐ", ANY, V5, Imperative),
+    rule("せ", "す", ANY, V5, Imperative),
+    rule("て", "つ", ANY, V5, Imperative),
+    rule("ね", "ぬ", ANY, V5, Imperative),
+    rule("べ", "ぶ", ANY, V5, Imperative),
+    rule("め", "む", ANY, V5, Imperative),
+    rule("れ", "る", ANY, V5, Imperative),
+    rule("こい", "くる", ANY, VK, Imperative),
+    rule("しろ", "する", ANY, VS, Imperative),
+    // Conditional
+    rule("れば", "る", ANY, V1, Conditional),
+    rule("えば", "う", ANY, V5, Conditional),
+    rule("けば", "く", ANY, V5, Conditional),
+    rule("げば", "ぐ", ANY, V5, Conditional),
+    rule("せば", "す", ANY, V5, Conditional),
+    rule("てば", "つ", ANY, V5, Conditional),
+    rule("ねば", "ぬ", ANY, V5, Conditional),
+    rule("べば", "ぶ", ANY, V5, Conditional),
+    rule("めば", "む", ANY, V5, Conditional),
+    rule("くれば", "くる", ANY, VK, Conditional),
+    rule("すれば", "する", ANY, VS, Conditional),
+    rule("ければ", "い", ANY, ADJ_I, Conditional),
+    // Adverbial
+    rule("く", "い", ANY, ADJ_I, Adverbial),
+];
+
+/// Max amount of rules applied to a single word
+const MAX_DEPTH: usize = 4;
+
+/// A possible dictionary form of an inflected word
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Deinflection {
+    pub word: String,
+    /// Inflections that were removed from the input
+    pub inflections: Inflections,
+}
+
+/// Returns all words `inp` could be an inflection of. Candidates are not checked against a
+/// dictionary, so most of them don't exist. `inp` itself is not part of the output. A word
+/// reached through different inflections (potential and passive of 食べられる) is returned
+/// once per set of inflections
+pub fn deinflect(inp: &str) -> Vec<Deinflection> {
+    let mut out: Vec<Deinflection> = vec![];
+    let mut seen: HashSet<(String, u8, Inflections)> = HashSet::new();
+
+    let mut current = vec![(inp.to_string(), ANY, Inflections::default())];
+
+    for _ in 0..MAX_DEPTH {
+        let mut next = vec![];
+
+        for (word, word_type, inflections) in &current {
+            for rule in RULES {
+                if rule.type_in & word_type == 0 {
+                    continue;
+                }
+
+                let stem = match word.strip_suffix(rule.from) {
+                    Some(stem) => stem,
+                    None => continue,
+                };
+
+                let new_word = format!("{stem}{}", rule.to);
+                let inflections = inflections.with(rule.inflection);
+                if new_word.is_empty()
+                    || !seen.insert((new_word.clone(), rule.type_out, inflections))
+                {
+                    continue;
+                }
+
+                let is_new = !out
+                    .iter()
+                    .any(|i| i.word == new_word && i.inflections == inflections);
+                // Intermediate forms are no dictionary forms
+                if rule.type_out & (MASU | TE | TA) == 0 && is_new {
+                    out.push(Deinflection {
+                        word: new_word.clone(),
+                        inflections,
+                    });
+                }
+
+                next.push((new_word, rule.type_out, inflections));
+            }
+        }
+
+        if next.is_empty() {
+            break;
+        }
+        current = next;
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn inflections(inflections: &[Inflection]) -> Inflections {
+        Inflections::from(inflections.to_vec())
+    }
+
+    fn contains(out: &[Deinflection], word: &str, expected: &[Inflection]) -> bool {
+        out.iter()
+            .any(|i| i.word == word && i.inflections == inflections(expected))
+    }
+
+    #[test]
+    fn test_past_passive_and_potential() {
+        let out = deinflect("食べられた");
+        assert!(contains(&out, "食べる", &[Past, Potential]));
+        assert!(contains(&out, "食べる", &[Past, Passive]));
+        assert!(contains(&out, "食べられる", &[Past]));
+    }
+
+    #[test]
+    fn test_no_duplicates() {
+        let out = deinflect("食べられた");
+        for (pos, a) in out.iter().enumerate() {
+            assert!(!out[pos + 1..].contains(a), "duplicate {a:?}");
+        }
+    }
+
+    #[test]
+    fn test_negative_past() {
+        let out = deinflect("食べなかった");
+        assert!(contains(&out, "食べる", &[Negative, Past]));
+    }
+
+    #[test]
+    fn test_godan_te_progressive() {
+        let out = deinflect("読んでいます");
+        assert!(contains(&out, "読む", &[Polite, Progressive, Te]));
+    }
+
+    #[test]
+    fn test_polite_past() {
+        let out = deinflect("食べました");
+        assert!(contains(&out, "食べる", &[Past, Polite]));
+    }
+
+    #[test]
+    fn test_no_match() {
+        assert!(deinflect("ねこ").iter().all(|i| i.word != "ねこ"));
+        assert!(deinflect("").is_empty());
+    }
+}
@@ -1,8 +1,9 @@
-use romaji::RomajiExt;
+use ::romaji::RomajiExt;
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    index::{Output, ToOutput},
+    index::{Output, Priority, ToOutput},
+    normalize::kana,
     relevance::item::EngineItem,
 };
 
@@ -15,6 +16,11 @@ pub struct Item {
 
     pub frequency: f64,
 
+    /// Reading of each part of `kanji` as (surface, reading) pairs
+    pub furigana: Option<Vec<(String, String)>>,
+
+    pub priority: Priority,
+
     // kana hashes
     pub(crate) hash: Option<jpeudex::Hash>,
 }
@@ -37,18 +43,34 @@ impl Item {
             kanji,
             alternative,
             frequency,
+            furigana: None,
+            priority: Priority::default(),
             hash,
         }
     }
+
+    /// Sets the priority metadata of the item
+    #[inline]
+    pub fn with_priority(mut self, priority: Priority) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Sets the reading alignment of the items kanji. `parts` holds the surface and reading
+    /// of each part, eg. [("食", "た"), ("べる", "べる")]
+    #[inline]
+    pub fn with_furigana(mut self, parts: Vec<(String, String)>) -> Self {
+        self.furigana = Some(parts);
+        self
+    }
 }
 
 impl ToOutput for Item {
     #[inline]
     fn to_output(&self) -> Output {
-        Output {
-            primary: self.kana.clone(),
-            secondary: self.kanji.clone(),
-        }
+        let mut output = Output::new(self.kana.clone(), self.kanji.clone());
+        output.furigana = self.furigana.clone();
+        output
     }
 }
 
@@ -60,39 +82,8 @@ impl super::super::IndexItem for Item {
 
     #[inline]
     fn str_relevance(&self, query: &str) -> u16 {
-        let query = query.to_hiragana();
-        fn freq(word: &str, query: &str) -> (u16, bool) {
-            let word = word.to_hiragana();
-            if word.starts_with(&query) {
-                let query_len: usize = query.chars().count();
-                let word_len: usize = word.chars().count();
-
-                let normalized = (1.0 - (query_len as f32 / word_len as f32)) * 1000.0;
-                let sim = (1000.0 - normalized) as u16;
-                (sim, true)
-            } else {
-                let sim = (strsim::normalized_levenshtein(&word, &query) * 1000.0) as u16;
-                (sim, false)
-            }
-        }
-
-        let (kanji_sc, kanji_sw) = self
-            .kanji
-            .as_ref()
-            .map(|i| freq(i, &query))
-            .unwrap_or((0, false));
-
-        let (kana_sc, kana_sw) = freq(&self.kana, &query);
-        if kana_sw || kanji_sw {
-            return kana_sc.max(kanji_sc) + 10;
-        }
-
-        self.alternative
-            .iter()
-            .map(|r| freq(r, &query).0)
-            .max()
-            .unwrap_or(0)
-            .saturating_sub(300)
+        let alternative = self.alternative.iter().map(|i| i.as_str());
+        reading_relevance(&self.kana, self.kanji.as_deref(), alternative, query)
     }
 
     #[inline]
@@ -100,8 +91,8 @@ impl super::super::IndexItem for Item {
         EngineItem::new(self, 0)
     }
 
-    fn terms(&self) -> Vec<&String> {
-        let mut out = vec![&self.kana];
+    fn terms(&self) -> Vec<&str> {
+        let mut out = vec![self.kana.as_str()];
         if let Some(k) = &self.kanji {
             out.push(k);
         }
@@ -113,6 +104,52 @@ impl super::super::IndexItem for Item {
     fn word_id(&self) -> u32 {
         self.word_id
     }
+
+    #[inline]
+    fn priority(&self) -> Priority {
+        self.priority
+    }
+}
+
+/// String relevance of a Japanese word with the given readings for `query`
+pub(crate) fn reading_relevance<'s, I>(
+    kana: &str,
+    kanji: Option<&str>,
+    alternative: I,
+    query: &str,
+) -> u16
+where
+    I: IntoIterator<Item = &'s str>,
+{
+    let query = kana::unify(query).to_hiragana();
+    fn freq(word: &str, query: &str) -> (u16, bool) {
+        let word = kana::unify(word).to_hiragana();
+        if word.starts_with(query) {
+            let query_len: usize = query.chars().count();
+            let word_len: usize = word.chars().count();
+
+            let normalized = (1.0 - (query_len as f32 / word_len as f32)) * 1000.0;
+            let sim = (1000.0 - normalized) as u16;
+            (sim, true)
+        } else {
+            let sim = (strsim::normalized_levenshtein(&word, query) * 1000.0) as u16;
+            (sim, false)
+        }
+    }
+
+    let (kanji_sc, kanji_sw) = kanji.map(|i| freq(i, &query)).unwrap_or((0, false));
+
+    let (kana_sc, kana_sw) = freq(kana, &query);
+    if kana_sw || kanji_sw {
+        return kana_sc.max(kanji_sc) + 10;
+    }
+
+    alternative
+        .into_iter()
+        .map(|r| freq(r, &query).0)
+        .max()
+        .unwrap_or(0)
+        .saturating_sub(300)
 }
 
 impl std::hash::Hash for Item {
@@ -0,0 +1,116 @@
+use std::{borrow::Borrow, io::Read};
+
+use ngindex::NGIndex;
+use qp_trie::{wrapper::BString, Trie};
+use serde::Deserialize;
+
+use super::{builder::JpIndexBulider, Item, JapaneseIndex};
+use crate::index::{file::Error, Priority};
+
+/// Item of a `JapaneseIndex` written before the index file format existed
+#[derive(Deserialize)]
+struct LegacyItem {
+    word_id: u32,
+    kana: String,
+    kanji: Option<String>,
+    alternative: Vec<String>,
+    frequency: f64,
+    hash: Option<jpeudex::Hash>,
+}
+
+impl From<LegacyItem> for Item {
+    #[inline]
+    fn from(item: LegacyItem) -> Self {
+        Self {
+            word_id: item.word_id,
+            kana: item.kana,
+            kanji: item.kanji,
+            alternative: item.alternative,
+            frequency: item.frequency,
+            furigana: None,
+            priority: Priority::default(),
+            hash: item.hash,
+        }
+    }
+}
+
+impl JapaneseIndex {
+    /// Loads an index that was serialized with plain bincode by versions before the index
+    /// file format existed, like the shipped `kanji_meanings`. Those files have no header and
+    /// store their readings in qp-tries, so all readings get indexed again. Files which end
+    /// before the n-gram index load with an empty one
+    pub fn load_legacy<R: Read>(mut inp: R) -> Result<Self, Error> {
+        let trie: Trie<BString, Vec<u32>> = bincode::deserialize_from(&mut inp)?;
+        let items: Vec<LegacyItem> = bincode::deserialize_from(&mut inp)?;
+        let kanji_align: Trie<BString, Vec<u32>> = bincode::deserialize_from(&mut inp)?;
+
+        let mut rest = vec![];
+        inp.read_to_end(&mut rest)?;
+        let ngindex: NGIndex<Vec<u32>> = if rest.is_empty() {
+            NGIndex::default()
+        } else {
+            bincode::deserialize(&rest)?
+        };
+
+        let mut builder = JpIndexBulider::new();
+        for item in items {
+            builder.insert::<&str>(&[], item.into());
+        }
+        for (reading, ids) in trie.iter() {
+            let reading = trie_key(reading)?;
+            for id in ids {
+                builder.add_readings(&[reading], *id);
+            }
+        }
+        for (reading, ids) in kanji_align.iter() {
+            let reading = trie_key(reading)?;
+            for id in ids {
+                builder.insert_kalign(&[reading], *id);
+            }
+        }
+
+        let mut index = builder.build();
+        index.ngindex = ngindex;
+        Ok(index)
+    }
+}
+
+/// Returns the key of a legacy trie as string
+#[inline]
+fn trie_key(key: &BString) -> Result<&str, Error> {
+    let key: &[u8] = key.borrow();
+    std::str::from_utf8(key)
+        .map_err(|err| Error::Encoding(Box::new(bincode::ErrorKind::InvalidUtf8Encoding(err))))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs::File, io::BufReader};
+
+    use super::*;
+    use crate::index::{SuggestionIndex, ToOutput};
+
+    #[test]
+    fn test_load_shipped_meanings() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/kanji_meanings");
+        let file = BufReader::new(File::open(path).unwrap());
+        let index = JapaneseIndex::load_legacy(file).unwrap();
+        assert_eq!(index.len(), 24761);
+
+        let found = index.exact("Prussia");
+        assert_eq!(found.len(), 1);
+        let output = found[0].inner().to_output();
+        assert_eq!(output.primary, "Prussia");
+        assert_eq!(output.secondary.as_deref(), Some("普"));
+
+        assert!(index
+            .predictions("Palladium", 10)
+            .iter()
+            .any(|i| i.inner().to_output().primary == "Palladium (element)"));
+    }
+
+    #[test]
+    fn test_not_a_legacy_index() {
+        assert!(JapaneseIndex::load_legacy(&[1u8, 2, 3][..]).is_err());
+    }
+}
@@ -1,25 +1,54 @@
 pub mod builder;
+pub mod convert;
+pub mod deinflect;
 pub mod item;
+mod legacy;
+pub mod typo;
 
 pub use item::Item;
 use ngindex::NGIndex;
 use serde::{Deserialize, Serialize};
+use typo::KanaCosts;
 
-use super::{IndexItem, KanjiReadingAlign, NGIndexable, SuggestionIndex};
-use crate::relevance::item::EngineItem;
-use order_struct::{float_ord::FloatOrd, order_nh::OrderVal, OrderBy};
+use super::{
+    file::IndexFile,
+    mmap::{MmapItem, MmapWriter},
+    prefix_tree::{PrefixTree, TreeView, EDIT_COST},
+    IndexItem, IndexKind, KanjiReadingAlign, NGIndexable, SuggestionIndex, WordIdMap,
+};
+use crate::{
+    normalize::{kana, Normalizer, NormalizerPipeline},
+    relevance::item::EngineItem,
+};
+use order_struct::{float_ord::FloatOrd, order_nh::OrderVal};
 use priority_container::{PrioContainer, PrioContainerMax};
 use qp_trie::{wrapper::BString, Trie};
-use std::collections::HashSet;
+use std::{
+    collections::HashSet,
+    io::{self, Write},
+};
 
-/// Japanese suggestion index
+/// Japanese suggestion index. Each reading is additionally indexed in its kana unified form
+/// (see `kana::unify`) and queries get unified the same way, so hiragana, katakana and
+/// half-width input all match each other.
+///
+/// Readings used to be stored in the public qp-trie field `trie`. It got replaced by a
+/// `PrefixTree`, which can be accessed with `tree`. This also changed the serialized layout,
+/// so files written by older versions can't be deserialized as `JapaneseIndex` anymore. Use
+/// `load_legacy` to load them
 #[derive(Serialize, Deserialize)]
 pub struct JapaneseIndex {
-    pub trie: Trie<BString, Vec<u32>>,
+    /// Character tree over all readings, used for exact, prefix and top-k lookups
+    tree: PrefixTree,
     pub items: Vec<Item>,
     kanji_align: Trie<BString, Vec<u32>>,
 
     ngindex: NGIndex<Vec<u32>>,
+
+    /// Normalizer that was applied to all readings
+    normalizer: NormalizerPipeline,
+    #[serde(skip)]
+    word_ids: WordIdMap,
 }
 
 impl JapaneseIndex {
@@ -27,36 +56,52 @@ impl JapaneseIndex {
     pub fn get_item(&self, id: u32) -> &Item {
         &self.items[id as usize]
     }
+
+    /// Returns the character tree over all readings. Its keys are the normalized and kana
+    /// unified readings, its values the positions of the items in `items`
+    #[inline]
+    pub fn tree(&self) -> TreeView<'_> {
+        self.tree.view()
+    }
+
+    /// Writes the index in a format that can be memory mapped using `MmapIndex`
+    pub fn write_mmap<W: Write>(&self, out: W) -> io::Result<()> {
+        let mut writer = MmapWriter::new(IndexKind::Japanese);
+        writer.set_normalizer(self.normalizer.clone());
+        for item in &self.items {
+            let furigana = item
+                .furigana
+                .as_deref()
+                .map(MmapItem::encode_furigana)
+                .unwrap_or_default();
+            let mut strings = vec![
+                item.kana.as_str(),
+                item.kanji.as_deref().unwrap_or_default(),
+                furigana.as_str(),
+            ];
+            strings.extend(item.alternative.iter().map(|i| i.as_str()));
+            writer.add_item(item.word_id, item.frequency, item.priority, &strings);
+        }
+        writer.write(self.tree.view(), out)
+    }
 }
 
 impl SuggestionIndex for JapaneseIndex {
     fn predictions(&self, inp: &str, limit: usize) -> Vec<EngineItem> {
-        let mut prio_container = PrioContainerMax::new_allocated(limit);
-        let mut pev_dups: HashSet<&Item> = HashSet::with_capacity(limit * 2);
-
-        let items = self.trie.iter_prefix_str(inp);
-        for j in items.map(|i| i.1).flatten() {
-            let word = self.get_item(*j);
-
-            if pev_dups.contains(word) {
-                continue;
-            }
-
-            prio_container.insert(OrderBy::new(word, |a, b| {
-                FloatOrd(a.frequency).cmp(&FloatOrd(b.frequency))
-            }));
-
-            pev_dups.insert(word);
-        }
+        let mut pev_dups: HashSet<&Item> = HashSet::with_capacity(limit);
 
-        // PrioContainer only yields `limit` items
-        prio_container
+        self.tree
+            .view()
+            .top_k(&kana::unify(inp), limit, |id| self.get_item(id).frequency)
             .into_iter()
-            .map(|i| i.0.into_inner().into_engine_item())
+            .map(|id| self.get_item(id))
+            .filter(|word| pev_dups.insert(*word))
+            .map(|word| word.into_engine_item())
             .collect()
     }
 
     fn similar_terms(&self, inp: &str, limit: usize, max_dist: u32) -> Vec<EngineItem> {
+        let inp = &kana::unify(inp);
         let inp_len = inp.trim().chars().count();
         if inp_len <= 1 {
             return vec![];
@@ -71,10 +116,10 @@ impl SuggestionIndex for JapaneseIndex {
         let prefix = inp.char_indices().nth(1).map(|i| &inp[0..i.0]).unwrap();
 
         let iter = self
-            .trie
-            .iter_prefix_str(prefix)
-            .map(|i| i.1.iter().map(|j| self.get_item(*j)))
-            .flatten();
+            .tree
+            .view()
+            .prefix_ids(prefix)
+            .map(|i| self.get_item(i));
 
         for term in iter {
             let hash = match &term.hash {
@@ -94,12 +139,32 @@ impl SuggestionIndex for JapaneseIndex {
         out.into_iter().collect()
     }
 
+    fn fuzzy_predictions(&self, inp: &str, limit: usize, max_dist: u32) -> Vec<EngineItem> {
+        self.tree
+            .view()
+            .fuzzy_top_k(
+                &kana::unify(inp),
+                limit,
+                max_dist * EDIT_COST,
+                &KanaCosts,
+                |id| self.get_item(id).frequency,
+            )
+            .into_iter()
+            .map(|(id, dist)| EngineItem::new(self.get_item(id), dist as u16))
+            .collect()
+    }
+
     #[inline]
     fn exact(&self, inp: &str) -> Vec<EngineItem> {
-        self.trie
-            .get_str(inp)
-            .map(|id| id.into_iter().filter_map(|i| self.get_word(*i)).collect())
-            .unwrap_or_default()
+        let tree = self.tree.view();
+        let node = match tree.find(&kana::unify(inp)) {
+            Some(n) => n,
+            None => return vec![],
+        };
+        tree.node_ids(node)
+            .iter()
+            .filter_map(|i| self.get_word(*i))
+            .collect()
     }
 
     #[inline]
@@ -107,16 +172,42 @@ impl SuggestionIndex for JapaneseIndex {
         self.items.get(id as usize).map(|i| i.into_engine_item())
     }
 
+    fn word_by_id(&self, word_id: u32) -> Option<EngineItem> {
+        let pos = self
+            .word_ids
+            .position(word_id, || self.items.iter().map(|i| i.word_id))?;
+        self.get_word(pos)
+    }
+
     #[inline]
     fn len(&self) -> usize {
         self.items.len()
     }
+
+    #[inline]
+    fn normalizer(&self) -> Option<&dyn Normalizer> {
+        Some(&self.normalizer)
+    }
+}
+
+impl IndexFile for JapaneseIndex {
+    const KIND: IndexKind = IndexKind::Japanese;
+
+    #[inline]
+    fn term_count(&self) -> usize {
+        self.items.len()
+    }
+
+    #[inline]
+    fn normalizer_config(&self) -> Option<String> {
+        Some(self.normalizer.config())
+    }
 }
 
 impl KanjiReadingAlign for JapaneseIndex {
     fn align_reading(&self, query: &str) -> Vec<EngineItem> {
         let mut out = HashSet::new();
-        for i in self.kanji_align.subtrie_str(query) {
+        for i in self.kanji_align.subtrie_str(&kana::unify(query)) {
             for word in i.1 {
                 out.insert(self.get_item(*word).into_engine_item());
             }
@@ -133,7 +224,7 @@ impl NGIndexable for JapaneseIndex {
         q_weight: f32,
         term_limit: usize,
     ) -> Vec<EngineItem> {
-        let q_vec = match self.ngindex.make_query_vec(&query) {
+        let q_vec = match self.ngindex.make_query_vec(&kana::unify(query)) {
             Some(q) => q,
             None => return vec![],
         };
@@ -145,7 +236,7 @@ impl NGIndexable for JapaneseIndex {
             .find_qweight_fast(&q_vec, q_weight, term_limit)
             .map(|(id, sim)| OrderVal::new(id, FloatOrd(sim)));
         prio_queue.extend(res_iter);
-        println!("ngindex found: {}", prio_queue.total_pushed());
+        log::trace!("ngindex found: {}", prio_queue.total_pushed());
 
         let mut out: Vec<_> = prio_queue
             .into_iter()
@@ -164,14 +255,7 @@ impl NGIndexable for JapaneseIndex {
     }
 }
 
-// Format inp
+// Format inp. Same as `NormalizerPipeline::japanese()`
 pub fn jp_format(inp: &str) -> String {
-    let mut out = inp.to_string();
-    let to_replace = &[
-        "(", ")", ".", ",", "/", "[", "]", "?", "!", "{", "}", "、", "。", "・",
-    ];
-    for tr in to_replace {
-        out = out.replace(tr, "");
-    }
-    out.to_lowercase()
+    NormalizerPipeline::japanese().normalize(inp)
 }
@@ -0,0 +1,134 @@
+use unicode_normalization::UnicodeNormalization;
+
+use crate::index::prefix_tree::{EditCosts, EDIT_COST};
+
+/// Cost of confusing two kana of the same class
+const CONFUSION_COST: u32 = EDIT_COST / 4;
+
+/// Cost of a missing or additional long vowel
+const LONG_VOWEL_COST: u32 = EDIT_COST / 2;
+
+/// Small kana and their regular forms
+#[rustfmt::skip]
+static SMALL_KANA: &[(char, char)] = &[
+    ('ぁ', 'あ'), ('ぃ', 'い'), ('ぅ', 'う'), ('ぇ', 'え'), ('ぉ', 'お'),
+    ('っ', 'つ'), ('ゃ', 'や'), ('ゅ', 'ゆ'), ('ょ', 'よ'), ('ゎ', 'わ'),
+];
+
+/// Edit costs for hiragana input which make common Japanese typos cheaper than other edits:
+/// missing or additional dakuten (か/が), small and regular kana (や/ゃ, つ/っ), long vowel
+/// spellings (おう/おお/おー) and missing っ or long vowels
+#[derive(Clone, Copy, Debug, Default)]
+pub struct KanaCosts;
+
+impl EditCosts for KanaCosts {
+    fn substitution(&self, query: char, key: char) -> u32 {
+        if without_dakuten(query) == without_dakuten(key)
+            || regular_kana(query) == regular_kana(key)
+            || same_long_vowel(query, key)
+        {
+            CONFUSION_COST
+        } else {
+            EDIT_COST
+        }
+    }
+
+    #[inline]
+    fn insertion(&self, key: char) -> u32 {
+        omission_cost(key)
+    }
+
+    #[inline]
+    fn deletion(&self, query: char) -> u32 {
+        omission_cost(query)
+    }
+}
+
+/// Cost of `c` being left out or typed additionally
+#[inline]
+fn omission_cost(c: char) -> u32 {
+    match c {
+        'っ' | 'ー' => CONFUSION_COST,
+        'う' | 'い' => LONG_VOWEL_COST,
+        _ => EDIT_COST,
+    }
+}
+
+/// Returns `c` without (han)dakuten
+#[inline]
+fn without_dakuten(c: char) -> char {
+    c.nfd().next().unwrap_or(c)
+}
+
+/// Returns the regular form of a small kana
+#[inline]
+fn regular_kana(c: char) -> char {
+    SMALL_KANA
+        .iter()
+        .find(|i| i.0 == c)
+        .map(|i| i.1)
+        .unwrap_or(c)
+}
+
+/// Returns `true` if both characters are a common way of writing the same long vowel
+#[inline]
+fn same_long_vowel(a: char, b: char) -> bool {
+    let vowels = ['あ', 'い', 'う', 'え', 'お'];
+    matches!(
+        (a, b),
+        ('ー', v) | (v, 'ー') if vowels.contains(&v)
+    ) || matches!(
+        (a, b),
+        ('う', 'お') | ('お', 'う') | ('い', 'え') | ('え', 'い')
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::index::test_util;
+
+    const WORDS: &[&str] = &["がっこう", "かっこう", "こうこう", "らーめん"];
+
+    /// Returns the distance of each found word for `query`
+    fn lookup(query: &str, max_cost: u32) -> Vec<(&'static str, u32)> {
+        let tree = test_util::prefix_tree(WORDS.iter().copied(), |_| 1.0);
+        let mut found: Vec<_> = tree
+            .view()
+            .fuzzy_top_k(query, WORDS.len(), max_cost, &KanaCosts, |_| 1.0)
+            .into_iter()
+            .map(|(id, dist)| (WORDS[id as usize], dist))
+            .collect();
+        found.sort_unstable();
+        found
+    }
+
+    #[test]
+    fn test_confusion_costs() {
+        let costs = KanaCosts;
+        assert_eq!(costs.substitution('か', 'が'), CONFUSION_COST);
+        assert_eq!(costs.substitution('つ', 'っ'), CONFUSION_COST);
+        assert_eq!(costs.substitution('ー', 'あ'), CONFUSION_COST);
+        assert_eq!(costs.substitution('か', 'さ'), EDIT_COST);
+        assert_eq!(costs.deletion('っ'), CONFUSION_COST);
+        assert_eq!(costs.insertion('う'), LONG_VOWEL_COST);
+    }
+
+    #[test]
+    fn test_missing_dakuten() {
+        let found = lookup("かっこう", CONFUSION_COST);
+        assert_eq!(found, vec![("かっこう", 0), ("がっこう", CONFUSION_COST)]);
+    }
+
+    #[test]
+    fn test_missing_sokuon() {
+        let found = lookup("がこう", CONFUSION_COST);
+        assert_eq!(found, vec![("がっこう", CONFUSION_COST)]);
+    }
+
+    #[test]
+    fn test_long_vowel() {
+        let found = lookup("らあめん", CONFUSION_COST);
+        assert_eq!(found, vec![("らーめん", CONFUSION_COST)]);
+    }
+}
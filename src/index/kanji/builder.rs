@@ -0,0 +1,75 @@
+use std::collections::HashMap;
+
+use super::{key, Item, KanjiIndex};
+use crate::{index::prefix_tree::PrefixTreeBuilder, normalize::NormalizerPipeline};
+
+/// Helper to build a new kanji index
+pub struct KanjiIndexBuilder {
+    tree: PrefixTreeBuilder,
+    items: Vec<Item>,
+    radicals: HashMap<char, Vec<u32>>,
+    normalizer: NormalizerPipeline,
+}
+
+impl KanjiIndexBuilder {
+    /// Create a new builder. Keys get normalized with `NormalizerPipeline::basic()` by default
+    pub fn new() -> Self {
+        Self {
+            tree: PrefixTreeBuilder::new(),
+            items: vec![],
+            radicals: HashMap::new(),
+            normalizer: NormalizerPipeline::basic(),
+        }
+    }
+
+    /// Sets the normalizer that gets applied to all meanings and readings. The index keeps it
+    /// to normalize queries the same way
+    pub fn set_normalizer(&mut self, normalizer: NormalizerPipeline) {
+        self.normalizer = normalizer;
+    }
+
+    /// Adds a kanji to the index. It can be found by its literal, meanings and readings.
+    /// Returns the id of the new item
+    pub fn insert(&mut self, item: Item) -> u32 {
+        let id = self.items.len() as u32;
+
+        self.insert_key(&item.literal.to_string(), id);
+        for meaning in item.meaning_keys() {
+            self.insert_key(&meaning, id);
+        }
+        for reading in item.readings() {
+            self.insert_key(&reading, id);
+        }
+
+        for radical in &item.radicals {
+            let ids = self.radicals.entry(*radical).or_default();
+            if ids.last() != Some(&id) {
+                ids.push(id);
+            }
+        }
+
+        self.items.push(item);
+        id
+    }
+
+    /// Create a KanjiIndex out of the builder
+    pub fn build(self) -> KanjiIndex {
+        let items = self.items;
+        let tree = self.tree.build(|id| items[id as usize].frequency);
+
+        KanjiIndex {
+            tree,
+            items,
+            radicals: self.radicals,
+            normalizer: self.normalizer,
+        }
+    }
+
+    fn insert_key(&mut self, term: &str, id: u32) {
+        let key = key(&self.normalizer, term);
+        if key.is_empty() {
+            return;
+        }
+        self.tree.insert(&key, id);
+    }
+}
@@ -0,0 +1,186 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    index::{IndexItem, Output, Priority, ToOutput},
+    normalize::kana,
+    relevance::item::EngineItem,
+};
+
+/// Min length of a word within a meaning to be used as key
+const MIN_WORD_LEN: usize = 3;
+
+/// A single kanji together with its dictionary data
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Item {
+    pub word_id: u32,
+    pub literal: char,
+    /// English meanings
+    pub meanings: Vec<String>,
+    pub onyomi: Vec<String>,
+    /// Kun readings. Okurigana may be separated by a dot (た.べる)
+    pub kunyomi: Vec<String>,
+    /// Radicals and parts the kanji is made of
+    pub radicals: Vec<char>,
+    pub stroke_count: u8,
+    pub jlpt: Option<u8>,
+    /// School grade the kanji is taught in
+    pub grade: Option<u8>,
+    pub frequency: f64,
+}
+
+impl Item {
+    /// Create a new Item
+    #[inline]
+    pub fn new(word_id: u32, literal: char, stroke_count: u8, frequency: f64) -> Self {
+        assert!(frequency <= 1.0);
+        Self {
+            word_id,
+            literal,
+            meanings: vec![],
+            onyomi: vec![],
+            kunyomi: vec![],
+            radicals: vec![],
+            stroke_count,
+            jlpt: None,
+            grade: None,
+            frequency,
+        }
+    }
+
+    #[inline]
+    pub fn with_meanings(mut self, meanings: Vec<String>) -> Self {
+        self.meanings = meanings;
+        self
+    }
+
+    #[inline]
+    pub fn with_readings(mut self, onyomi: Vec<String>, kunyomi: Vec<String>) -> Self {
+        self.onyomi = onyomi;
+        self.kunyomi = kunyomi;
+        self
+    }
+
+    #[inline]
+    pub fn with_radicals(mut self, radicals: Vec<char>) -> Self {
+        self.radicals = radicals;
+        self
+    }
+
+    #[inline]
+    pub fn with_jlpt(mut self, jlpt: u8) -> Self {
+        self.jlpt = Some(jlpt);
+        self
+    }
+
+    #[inline]
+    pub fn with_grade(mut self, grade: u8) -> Self {
+        self.grade = Some(grade);
+        self
+    }
+
+    /// Returns all lowercased meanings together with each of their words that is at least
+    /// `MIN_WORD_LEN` characters long, so "to drink" can be found by "drink"
+    pub fn meaning_keys(&self) -> Vec<String> {
+        let mut out = vec![];
+        for meaning in self.meanings.iter().map(|i| i.to_lowercase()) {
+            let words: Vec<&str> = meaning.split_whitespace().collect();
+            if words.len() > 1 {
+                out.extend(
+                    words
+                        .into_iter()
+                        .filter(|w| w.chars().count() >= MIN_WORD_LEN)
+                        .map(|w| w.to_string()),
+                );
+            }
+            out.push(meaning);
+        }
+        out
+    }
+
+    /// Returns all on and kun readings without okurigana separators
+    pub fn readings(&self) -> impl Iterator<Item = String> + '_ {
+        self.onyomi
+            .iter()
+            .chain(self.kunyomi.iter())
+            .map(|i| plain_reading(i))
+    }
+}
+
+/// Removes okurigana separators and affix markers from a reading (-た.べる -> たべる)
+#[inline]
+pub(crate) fn plain_reading(reading: &str) -> String {
+    reading
+        .chars()
+        .filter(|c| !matches!(c, '.' | '-'))
+        .collect()
+}
+
+impl ToOutput for Item {
+    #[inline]
+    fn to_output(&self) -> Output {
+        let meanings = (!self.meanings.is_empty()).then(|| self.meanings.join(", "));
+        Output::new(self.literal.to_string(), meanings)
+    }
+}
+
+impl IndexItem for Item {
+    #[inline]
+    fn frequency(&self) -> f64 {
+        self.frequency
+    }
+
+    #[inline]
+    fn word_id(&self) -> u32 {
+        self.word_id
+    }
+
+    fn str_relevance(&self, query: &str) -> u16 {
+        let query = kana::unify(&query.to_lowercase());
+        if query.chars().eq(std::iter::once(self.literal)) {
+            return 1000;
+        }
+
+        let prefix_rel = self
+            .meaning_keys()
+            .into_iter()
+            .chain(self.readings().map(|i| kana::unify(&i)))
+            .filter(|term| term.starts_with(&query))
+            .map(|term| {
+                let normalized = 1.0 - (query.len() as f32 / term.len() as f32);
+                1000 - (normalized * 1000.0) as u16
+            })
+            .max();
+        if let Some(rel) = prefix_rel {
+            return rel;
+        }
+
+        self.meanings
+            .iter()
+            .map(|i| (strsim::normalized_levenshtein(&i.to_lowercase(), &query) * 1000.0) as u16)
+            .max()
+            .unwrap_or(0)
+            .saturating_sub(300)
+    }
+
+    #[inline]
+    fn priority(&self) -> Priority {
+        Priority {
+            jlpt: self.jlpt,
+            ..Priority::default()
+        }
+    }
+
+    #[inline]
+    fn into_engine_item(&self) -> EngineItem {
+        EngineItem::new(self, 0)
+    }
+
+    fn terms(&self) -> Vec<&str> {
+        self.meanings
+            .iter()
+            .chain(self.onyomi.iter())
+            .chain(self.kunyomi.iter())
+            .map(|i| i.as_str())
+            .collect()
+    }
+}
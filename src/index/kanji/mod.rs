@@ -0,0 +1,280 @@
+pub mod builder;
+pub mod item;
+
+pub use item::Item;
+
+use std::{collections::HashMap, ops::RangeInclusive};
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    file::IndexFile,
+    prefix_tree::{Levenshtein, PrefixTree, EDIT_COST},
+    IndexItem, IndexKind, SuggestionIndex,
+};
+use crate::{
+    normalize::{kana, Normalizer, NormalizerPipeline},
+    relevance::item::EngineItem,
+};
+
+/// Index for single kanji. Kanji can be completed by their English meanings ("wat" -> 水),
+/// their readings or looked up by radicals and stroke count using `find`
+#[derive(Serialize, Deserialize)]
+pub struct KanjiIndex {
+    /// Character tree holding the highest frequency of each subtree for fast top-k lookups
+    tree: PrefixTree,
+    items: Vec<Item>,
+    /// IDs of all kanji containing a radical, sorted ascending
+    radicals: HashMap<char, Vec<u32>>,
+
+    /// Normalizer that was applied to all keys
+    normalizer: NormalizerPipeline,
+}
+
+/// Constraints for looking up kanji. Empty constraints match all kanji
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct KanjiFilter {
+    /// Radicals that all have to be part of the kanji
+    pub radicals: Vec<char>,
+    pub strokes: Option<RangeInclusive<u8>>,
+    pub jlpt: Option<u8>,
+    pub grade: Option<u8>,
+}
+
+impl KanjiFilter {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[inline]
+    pub fn with_radical(mut self, radical: char) -> Self {
+        self.radicals.push(radical);
+        self
+    }
+
+    #[inline]
+    pub fn with_strokes(mut self, strokes: RangeInclusive<u8>) -> Self {
+        self.strokes = Some(strokes);
+        self
+    }
+
+    #[inline]
+    pub fn with_jlpt(mut self, jlpt: u8) -> Self {
+        self.jlpt = Some(jlpt);
+        self
+    }
+
+    #[inline]
+    pub fn with_grade(mut self, grade: u8) -> Self {
+        self.grade = Some(grade);
+        self
+    }
+
+    /// Returns `true` if the filter doesn't have any constraints
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Returns `true` if `item` fulfills all constraints
+    pub fn matches(&self, item: &Item) -> bool {
+        self.radicals.iter().all(|r| item.radicals.contains(r))
+            && !matches!(&self.strokes, Some(s) if !s.contains(&item.stroke_count))
+            && (self.jlpt.is_none() || self.jlpt == item.jlpt)
+            && (self.grade.is_none() || self.grade == item.grade)
+    }
+}
+
+impl KanjiIndex {
+    #[inline]
+    pub fn get_item(&self, id: u32) -> &Item {
+        &self.items[id as usize]
+    }
+
+    /// Returns the kanji with the given literal
+    pub fn get_kanji(&self, literal: char) -> Option<&Item> {
+        self.key_ids(&literal.to_string())
+            .iter()
+            .map(|i| self.get_item(*i))
+            .find(|i| i.literal == literal)
+    }
+
+    /// Returns up to `limit` kanji matching `filter`, ordered by frequency
+    pub fn find(&self, filter: &KanjiFilter, limit: usize) -> Vec<EngineItem> {
+        let mut found: Vec<&Item> = match self.radical_candidates(&filter.radicals) {
+            Some(ids) => ids.into_iter().map(|i| self.get_item(i)).collect(),
+            None => self.items.iter().collect(),
+        };
+        found.retain(|i| filter.matches(i));
+        found.sort_by(|a, b| b.frequency.total_cmp(&a.frequency));
+
+        found
+            .into_iter()
+            .take(limit)
+            .map(|i| i.into_engine_item())
+            .collect()
+    }
+
+    /// Returns the IDs of all kanji containing all `radicals` or `None` if `radicals` is empty
+    fn radical_candidates(&self, radicals: &[char]) -> Option<Vec<u32>> {
+        let mut lists = radicals
+            .iter()
+            .map(|r| {
+                self.radicals
+                    .get(r)
+                    .map(|i| i.as_slice())
+                    .unwrap_or_default()
+            })
+            .collect::<Vec<_>>();
+        lists.sort_by_key(|i| i.len());
+
+        let (first, rest) = lists.split_first()?;
+        let out = first
+            .iter()
+            .copied()
+            .filter(|id| rest.iter().all(|l| l.binary_search(id).is_ok()))
+            .collect();
+        Some(out)
+    }
+
+    /// Returns the IDs of all kanji with a key equal to `inp`
+    fn key_ids(&self, inp: &str) -> &[u32] {
+        let tree = self.tree.view();
+        match tree.find(&self.lookup_key(inp)) {
+            Some(node) => tree.node_ids(node),
+            None => &[],
+        }
+    }
+
+    /// Normalizes a query the same way the keys were normalized
+    #[inline]
+    fn lookup_key(&self, inp: &str) -> String {
+        key(&self.normalizer, inp)
+    }
+}
+
+/// Returns the key of a literal, meaning or reading. Used for building and querying so both
+/// normalize in the same order
+#[inline]
+fn key(normalizer: &NormalizerPipeline, term: &str) -> String {
+    kana::unify(&normalizer.normalize(term))
+}
+
+impl SuggestionIndex for KanjiIndex {
+    fn predictions(&self, inp: &str, limit: usize) -> Vec<EngineItem> {
+        self.tree
+            .view()
+            .top_k(&self.lookup_key(inp), limit, |id| {
+                self.get_item(id).frequency
+            })
+            .into_iter()
+            .map(|id| self.get_item(id).into_engine_item())
+            .collect()
+    }
+
+    fn fuzzy_predictions(&self, inp: &str, limit: usize, max_dist: u32) -> Vec<EngineItem> {
+        self.tree
+            .view()
+            .fuzzy_top_k(
+                &self.lookup_key(inp),
+                limit,
+                max_dist * EDIT_COST,
+                &Levenshtein,
+                |id| self.get_item(id).frequency,
+            )
+            .into_iter()
+            .map(|(id, dist)| EngineItem::new(self.get_item(id), dist as u16))
+            .collect()
+    }
+
+    #[inline]
+    fn exact(&self, inp: &str) -> Vec<EngineItem> {
+        self.key_ids(inp)
+            .iter()
+            .filter_map(|i| self.get_word(*i))
+            .collect()
+    }
+
+    #[inline]
+    fn get_word(&self, id: u32) -> Option<EngineItem> {
+        self.items.get(id as usize).map(|i| i.into_engine_item())
+    }
+
+    #[inline]
+    fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    #[inline]
+    fn normalizer(&self) -> Option<&dyn Normalizer> {
+        Some(&self.normalizer)
+    }
+}
+
+impl IndexFile for KanjiIndex {
+    const KIND: IndexKind = IndexKind::Kanji;
+
+    #[inline]
+    fn term_count(&self) -> usize {
+        self.items.len()
+    }
+
+    #[inline]
+    fn normalizer_config(&self) -> Option<String> {
+        Some(self.normalizer.config())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{builder::KanjiIndexBuilder, *};
+    use crate::index::ToOutput;
+
+    fn index() -> KanjiIndex {
+        let mut builder = KanjiIndexBuilder::new();
+        let water = Item::new(0, '水', 4, 0.9)
+            .with_meanings(vec!["Water".to_string()])
+            .with_readings(vec!["スイ".to_string()], vec!["みず".to_string()]);
+        let drink = Item::new(1, '飲', 12, 0.5)
+            .with_meanings(vec!["to drink".to_string()])
+            .with_readings(vec!["イン".to_string()], vec!["の.む".to_string()]);
+        builder.insert(water);
+        builder.insert(drink);
+        builder.build()
+    }
+
+    fn literals(items: Vec<EngineItem>) -> Vec<String> {
+        items
+            .iter()
+            .map(|i| i.inner().to_output().primary)
+            .collect()
+    }
+
+    #[test]
+    fn test_exact() {
+        let index = index();
+        assert_eq!(literals(index.exact("water")), vec!["水"]);
+        assert_eq!(literals(index.exact("ＷＡＴＥＲ")), vec!["水"]);
+        assert_eq!(literals(index.exact("すい")), vec!["水"]);
+        assert_eq!(literals(index.exact("スイ")), vec!["水"]);
+        assert_eq!(literals(index.exact("のむ")), vec!["飲"]);
+        assert_eq!(literals(index.exact("drink")), vec!["飲"]);
+        assert!(index.exact("wat").is_empty());
+    }
+
+    #[test]
+    fn test_get_kanji() {
+        let index = index();
+        assert_eq!(index.get_kanji('飲').unwrap().word_id, 1);
+        assert_eq!(index.get_kanji('水').unwrap().word_id, 0);
+        assert!(index.get_kanji('火').is_none());
+    }
+
+    #[test]
+    fn test_predictions() {
+        let index = index();
+        assert_eq!(literals(index.predictions("wat", 10)), vec!["水"]);
+        assert_eq!(literals(index.predictions("イ", 10)), vec!["飲"]);
+    }
+}
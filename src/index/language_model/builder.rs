@@ -0,0 +1,154 @@
+use std::collections::HashMap;
+
+use super::{Continuations, Item, LanguageModel};
+use crate::{
+    index::prefix_tree::PrefixTreeBuilder,
+    normalize::{Normalizer, NormalizerPipeline},
+};
+
+/// Helper to build a new language model out of a corpus or precomputed n-gram counts
+pub struct LanguageModelBuilder {
+    words: Vec<String>,
+    vocab: HashMap<String, u32>,
+    unigrams: Vec<u64>,
+    bigrams: HashMap<u32, HashMap<u32, u32>>,
+    trigrams: HashMap<[u32; 2], HashMap<u32, u32>>,
+    normalizer: NormalizerPipeline,
+}
+
+impl LanguageModelBuilder {
+    /// Create a new builder. Text gets normalized with `NormalizerPipeline::basic()` by
+    /// default
+    pub fn new() -> Self {
+        Self {
+            words: vec![],
+            vocab: HashMap::new(),
+            unigrams: vec![],
+            bigrams: HashMap::new(),
+            trigrams: HashMap::new(),
+            normalizer: NormalizerPipeline::basic(),
+        }
+    }
+
+    /// Sets the normalizer that gets applied to all text. The model keeps it to normalize
+    /// queries the same way
+    pub fn set_normalizer(&mut self, normalizer: NormalizerPipeline) {
+        self.normalizer = normalizer;
+    }
+
+    /// Counts all uni-, bi- and trigrams of a sentence. Tokens are separated by whitespace
+    pub fn add_text(&mut self, text: &str) {
+        let text = self.normalizer.normalize(text);
+        let ids: Vec<u32> = text.split_whitespace().map(|i| self.word(i)).collect();
+
+        for (pos, id) in ids.iter().enumerate() {
+            self.unigrams[*id as usize] += 1;
+            if pos >= 1 {
+                self.add_bigram(ids[pos - 1], *id, 1);
+            }
+            if pos >= 2 {
+                self.add_trigram([ids[pos - 2], ids[pos - 1]], *id, 1);
+            }
+        }
+    }
+
+    /// Adds a precomputed count of a single n-gram with one to three tokens. Longer n-grams
+    /// get ignored
+    pub fn add_ngram<S: AsRef<str>>(&mut self, tokens: &[S], count: u32) {
+        let tokens: Vec<String> = tokens
+            .iter()
+            .map(|i| self.normalizer.normalize(i.as_ref()))
+            .collect();
+        if tokens.iter().any(|i| i.trim().is_empty()) {
+            return;
+        }
+        let ids: Vec<u32> = tokens.iter().map(|i| self.word(i)).collect();
+
+        match ids[..] {
+            [a] => self.unigrams[a as usize] += count as u64,
+            [a, b] => self.add_bigram(a, b, count),
+            [a, b, c] => self.add_trigram([a, b], c, count),
+            _ => (),
+        }
+    }
+
+    /// Create a LanguageModel out of the builder
+    pub fn build(self) -> LanguageModel {
+        let total = self.unigrams.iter().sum::<u64>().max(1);
+
+        let items: Vec<Item> = self
+            .words
+            .into_iter()
+            .zip(self.unigrams)
+            .enumerate()
+            .map(|(id, (word, count))| Item {
+                word_id: id as u32,
+                word,
+                frequency: count as f64 / total as f64,
+            })
+            .collect();
+
+        let mut tree = PrefixTreeBuilder::new();
+        for item in &items {
+            tree.insert(&item.word, item.word_id);
+        }
+        let tree = tree.build(|id| items[id as usize].frequency);
+
+        LanguageModel {
+            items,
+            vocab: self.vocab,
+            tree,
+            bigrams: build_continuations(self.bigrams),
+            trigrams: build_continuations(self.trigrams),
+            normalizer: self.normalizer,
+        }
+    }
+
+    /// Returns the ID of `word`, adding it to the vocabulary if it's new
+    fn word(&mut self, word: &str) -> u32 {
+        if let Some(id) = self.vocab.get(word) {
+            return *id;
+        }
+
+        let id = self.words.len() as u32;
+        self.words.push(word.to_string());
+        self.vocab.insert(word.to_string(), id);
+        self.unigrams.push(0);
+        id
+    }
+
+    #[inline]
+    fn add_bigram(&mut self, prev: u32, word: u32, count: u32) {
+        *self
+            .bigrams
+            .entry(prev)
+            .or_default()
+            .entry(word)
+            .or_default() += count;
+    }
+
+    #[inline]
+    fn add_trigram(&mut self, prev: [u32; 2], word: u32, count: u32) {
+        *self
+            .trigrams
+            .entry(prev)
+            .or_default()
+            .entry(word)
+            .or_default() += count;
+    }
+}
+
+/// Turns the counts of each context into continuations ordered by count
+fn build_continuations<K: std::hash::Hash + Eq>(
+    counts: HashMap<K, HashMap<u32, u32>>,
+) -> HashMap<K, Continuations> {
+    counts
+        .into_iter()
+        .map(|(context, words)| {
+            let mut next: Vec<(u32, u32)> = words.into_iter().collect();
+            next.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+            let total = next.iter().map(|i| i.1 as u64).sum();
+            (context, Continuations { total, next })
+        })
+        .collect()
+}
@@ -0,0 +1,68 @@
+use serde::{Deserialize, Serialize};
+
+use super::split_query;
+use crate::{
+    index::{IndexItem, Output, ToOutput},
+    relevance::item::EngineItem,
+};
+
+/// A word of the vocabulary of a language model
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Item {
+    pub(crate) word_id: u32,
+    pub(crate) word: String,
+    /// Share of all words of the corpus
+    pub(crate) frequency: f64,
+}
+
+impl Item {
+    #[inline]
+    pub fn word(&self) -> &str {
+        &self.word
+    }
+}
+
+impl ToOutput for Item {
+    #[inline]
+    fn to_output(&self) -> Output {
+        Output::new(self.word.clone(), None)
+    }
+}
+
+impl IndexItem for Item {
+    #[inline]
+    fn frequency(&self) -> f64 {
+        self.frequency
+    }
+
+    #[inline]
+    fn word_id(&self) -> u32 {
+        self.word_id
+    }
+
+    /// Relevance of the word to the last token of `query`
+    fn str_relevance(&self, query: &str) -> u16 {
+        let (_, prefix) = split_query(query);
+        if prefix.is_empty() {
+            return 0;
+        }
+
+        if self.word.starts_with(prefix) {
+            let normalized = 1.0 - (prefix.len() as f32 / self.word.len() as f32);
+            1000 - (normalized * 1000.0) as u16
+        } else {
+            let sim = strsim::normalized_levenshtein(&self.word, prefix);
+            ((sim * 1000.0) as u16).saturating_sub(300)
+        }
+    }
+
+    #[inline]
+    fn into_engine_item(&self) -> EngineItem {
+        EngineItem::new(self, 0)
+    }
+
+    #[inline]
+    fn terms(&self) -> Vec<&str> {
+        vec![&self.word]
+    }
+}
@@ -0,0 +1,286 @@
+pub mod builder;
+pub mod item;
+
+pub use item::Item;
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::{file::IndexFile, prefix_tree::PrefixTree, IndexItem, IndexKind, SuggestionIndex};
+use crate::{
+    normalize::{Normalizer, NormalizerPipeline},
+    relevance::item::EngineItem,
+};
+
+/// Multiplier applied to the score of a word each time the model has to back off to a
+/// shorter context
+const BACKOFF: f64 = 0.4;
+
+/// Word level bigram and trigram model. Completes the last token of a multi-word input
+/// based on the tokens before it ("new y" -> york) and predicts the next word after a
+/// completed one ("new " -> york). Uses stupid backoff to fall back to shorter contexts.
+///
+/// Items returned by the model are single words which complete or follow the last token.
+#[derive(Serialize, Deserialize)]
+pub struct LanguageModel {
+    /// Vocabulary, with the vector position as ID
+    items: Vec<Item>,
+    vocab: HashMap<String, u32>,
+    /// Character tree over all words for completions without context
+    tree: PrefixTree,
+    /// Words following a single word
+    bigrams: HashMap<u32, Continuations>,
+    /// Words following two words
+    trigrams: HashMap<[u32; 2], Continuations>,
+    /// Normalizer that was applied to the corpus
+    normalizer: NormalizerPipeline,
+}
+
+/// Words following a context together with their counts
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub(crate) struct Continuations {
+    /// Amount of all words following the context
+    pub(crate) total: u64,
+    /// Following words and their counts, ordered by count descending
+    pub(crate) next: Vec<(u32, u32)>,
+}
+
+impl LanguageModel {
+    #[inline]
+    pub fn get_item(&self, id: u32) -> &Item {
+        &self.items[id as usize]
+    }
+
+    /// Returns the ID of a normalized word
+    #[inline]
+    pub fn word_id(&self, word: &str) -> Option<u32> {
+        self.vocab.get(word).copied()
+    }
+
+    /// Returns up to `limit` words starting with `prefix` that follow the tokens of
+    /// `context`, ordered by their score descending. Only the last two tokens of the context
+    /// are used. An empty prefix predicts the next word. Tokens have to be normalized
+    pub fn continuations(&self, context: &[&str], prefix: &str, limit: usize) -> Vec<(u32, f64)> {
+        let context: Vec<Option<u32>> = context[context.len().saturating_sub(2)..]
+            .iter()
+            .map(|i| self.word_id(i))
+            .collect();
+
+        let mut scores: HashMap<u32, f64> = HashMap::new();
+        let mut backoff = 1.0;
+
+        if let [Some(a), Some(b)] = &context[..] {
+            if let Some(cont) = self.trigrams.get(&[*a, *b]) {
+                self.add_continuations(&mut scores, cont, prefix, backoff, limit);
+            }
+        }
+        if context.len() == 2 {
+            backoff *= BACKOFF;
+        }
+
+        if let Some(Some(prev)) = context.last() {
+            if let Some(cont) = self.bigrams.get(prev) {
+                self.add_continuations(&mut scores, cont, prefix, backoff, limit);
+            }
+        }
+        if !context.is_empty() {
+            backoff *= BACKOFF;
+        }
+
+        // Without a prefix all words would match, so only observed continuations get predicted
+        if !prefix.is_empty() && scores.len() < limit {
+            let freq = |id: u32| self.items[id as usize].frequency;
+            for id in self.tree.view().top_k(prefix, limit, freq) {
+                scores.entry(id).or_insert(backoff * freq(id));
+            }
+        }
+
+        let mut out: Vec<_> = scores.into_iter().collect();
+        out.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+        out.truncate(limit);
+        out
+    }
+
+    /// Same as `continuations` but splits a normalized query into its context and the
+    /// prefix of the last token
+    pub fn query_continuations(&self, query: &str, limit: usize) -> Vec<EngineItem> {
+        let (context, prefix) = split_query(query);
+        self.continuations(&context, prefix, limit)
+            .into_iter()
+            .map(|(id, score)| {
+                let mut item = self.items[id as usize].into_engine_item();
+                item.set_relevance(score_relevance(score));
+                item
+            })
+            .collect()
+    }
+
+    /// Adds up to `limit` continuations starting with `prefix` that aren't scored yet
+    fn add_continuations(
+        &self,
+        scores: &mut HashMap<u32, f64>,
+        cont: &Continuations,
+        prefix: &str,
+        backoff: f64,
+        limit: usize,
+    ) {
+        let total = cont.total.max(1) as f64;
+        let matching = cont
+            .next
+            .iter()
+            .filter(|(id, _)| self.items[*id as usize].word.starts_with(prefix))
+            .take(limit);
+        for (id, count) in matching {
+            scores.entry(*id).or_insert(backoff * *count as f64 / total);
+        }
+    }
+}
+
+/// Splits a query into its context tokens and the prefix of the last token. The prefix is
+/// empty if the query ends with whitespace
+pub fn split_query(query: &str) -> (Vec<&str>, &str) {
+    let mut tokens: Vec<&str> = query.split_whitespace().collect();
+    if query.ends_with(char::is_whitespace) {
+        return (tokens, "");
+    }
+    let prefix = tokens.pop().unwrap_or("");
+    (tokens, prefix)
+}
+
+/// Converts a score of the model into a string relevance
+#[inline]
+pub fn score_relevance(score: f64) -> u16 {
+    ((score.sqrt() * 1000.0) as u16).clamp(1, 1000)
+}
+
+impl SuggestionIndex for LanguageModel {
+    #[inline]
+    fn predictions(&self, inp: &str, limit: usize) -> Vec<EngineItem> {
+        self.query_continuations(inp, limit)
+    }
+
+    fn exact(&self, inp: &str) -> Vec<EngineItem> {
+        self.word_id(inp.trim())
+            .map(|id| vec![self.items[id as usize].into_engine_item()])
+            .unwrap_or_default()
+    }
+
+    #[inline]
+    fn get_word(&self, id: u32) -> Option<EngineItem> {
+        self.items.get(id as usize).map(|i| i.into_engine_item())
+    }
+
+    /// Word IDs of the model are the positions of the words
+    #[inline]
+    fn word_by_id(&self, word_id: u32) -> Option<EngineItem> {
+        self.get_word(word_id)
+    }
+
+    #[inline]
+    fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    #[inline]
+    fn normalizer(&self) -> Option<&dyn Normalizer> {
+        Some(&self.normalizer)
+    }
+}
+
+impl IndexFile for LanguageModel {
+    const KIND: IndexKind = IndexKind::LanguageModel;
+
+    #[inline]
+    fn term_count(&self) -> usize {
+        self.items.len()
+    }
+
+    #[inline]
+    fn normalizer_config(&self) -> Option<String> {
+        Some(self.normalizer.config())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{builder::LanguageModelBuilder, *};
+
+    fn model() -> LanguageModel {
+        let mut builder = LanguageModelBuilder::new();
+        for text in ["New York City", "new york times", "new jersey", "old york"] {
+            builder.add_text(text);
+        }
+        builder.build()
+    }
+
+    fn assert_scores(found: Vec<(u32, f64)>, expected: &[(&str, f64)], model: &LanguageModel) {
+        assert_eq!(found.len(), expected.len(), "{found:?}");
+        for ((id, score), (word, exp_score)) in found.iter().zip(expected) {
+            assert_eq!(model.get_item(*id).word(), *word);
+            assert!(
+                (score - exp_score).abs() < 1e-9,
+                "{word}: {score} != {exp_score}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_bigram() {
+        let model = model();
+        let found = model.continuations(&["new"], "", 5);
+        assert_scores(found, &[("york", 2.0 / 3.0), ("jersey", 1.0 / 3.0)], &model);
+    }
+
+    #[test]
+    fn test_trigram() {
+        let model = model();
+        let found = model.continuations(&["new", "york"], "", 5);
+        assert_scores(found, &[("city", 0.5), ("times", 0.5)], &model);
+    }
+
+    #[test]
+    fn test_backoff_to_bigram() {
+        let model = model();
+        let found = model.continuations(&["old", "york"], "", 5);
+        let score = BACKOFF * 0.5;
+        assert_scores(found, &[("city", score), ("times", score)], &model);
+    }
+
+    #[test]
+    fn test_backoff_to_unigram() {
+        let model = model();
+        let found = model.continuations(&["old"], "j", 5);
+        assert_scores(found, &[("jersey", BACKOFF * 0.1)], &model);
+
+        // Unknown context words still back off
+        let found = model.continuations(&["xyz"], "ti", 5);
+        assert_scores(found, &[("times", BACKOFF * 0.1)], &model);
+
+        // Without prefix only observed continuations are predicted
+        assert!(model.continuations(&["xyz"], "", 5).is_empty());
+    }
+
+    #[test]
+    fn test_split_query() {
+        assert_eq!(split_query("new y"), (vec!["new"], "y"));
+        assert_eq!(split_query("new york "), (vec!["new", "york"], ""));
+        assert_eq!(split_query(""), (vec![], ""));
+    }
+
+    #[test]
+    fn test_score_relevance() {
+        assert_eq!(score_relevance(0.0), 1);
+        assert_eq!(score_relevance(0.25), 500);
+        assert_eq!(score_relevance(1.0), 1000);
+    }
+
+    #[test]
+    fn test_index() {
+        let model = model();
+        let found = model.predictions("new ", 5);
+        assert_eq!(found[0].inner().word_id(), model.word_id("york").unwrap());
+        assert_eq!(model.exact("jersey").len(), 1);
+        assert_eq!(model.len(), 6);
+    }
+}
@@ -0,0 +1,184 @@
+use crate::{
+    index::{basic, japanese, IndexItem, IndexKind, Output, Priority, ToOutput},
+    relevance::item::EngineItem,
+};
+
+/// Item of a memory mapped index. All strings are borrowed from the mapped file.
+///
+/// A record consists of the frequency (f64), the word ID (u32), the priority (common flag
+/// (u8), JLPT level (u8), two padding bytes and news rank (u32), zero if unknown) and a list
+/// of strings, each prefixed with its length (u32). Basic items store their word only. Japanese items store
+/// the kana reading, the kanji reading (empty if none), the furigana (empty if none) and all
+/// alternative readings. Furigana are stored as surfaces and readings, all separated by
+/// `FURIGANA_SEPARATOR`.
+pub struct MmapItem<'a> {
+    kind: IndexKind,
+    word_id: u32,
+    frequency: f64,
+    priority: Priority,
+    strings: &'a [u8],
+}
+
+/// Length of a records fixed size part
+pub(crate) const RECORD_HEADER_LEN: usize = 20;
+
+/// Separator of the parts of stored furigana
+const FURIGANA_SEPARATOR: char = '\u{1f}';
+
+impl<'a> MmapItem<'a> {
+    /// Parses an item out of a raw record. Records have to be at least `RECORD_HEADER_LEN`
+    /// bytes long, which `MmapIndex` checks on opening
+    #[inline]
+    pub(crate) fn parse(kind: IndexKind, record: &'a [u8]) -> Self {
+        let frequency = f64::from_le_bytes(record[0..8].try_into().unwrap());
+        let word_id = u32::from_le_bytes(record[8..12].try_into().unwrap());
+        let news_rank = u32::from_le_bytes(record[16..20].try_into().unwrap());
+        let priority = Priority {
+            common: record[12] != 0,
+            jlpt: (record[13] != 0).then_some(record[13]),
+            news_rank: (news_rank != 0).then_some(news_rank),
+        };
+        Self {
+            kind,
+            word_id,
+            frequency,
+            priority,
+            strings: &record[RECORD_HEADER_LEN..],
+        }
+    }
+
+    /// Encodes an item into a raw record
+    pub(crate) fn encode(
+        word_id: u32,
+        frequency: f64,
+        priority: Priority,
+        strings: &[&str],
+        out: &mut Vec<u8>,
+    ) {
+        out.extend(frequency.to_le_bytes());
+        out.extend(word_id.to_le_bytes());
+        out.push(priority.common as u8);
+        out.push(priority.jlpt.unwrap_or(0));
+        out.extend([0u8; 2]);
+        out.extend(priority.news_rank.unwrap_or(0).to_le_bytes());
+        for s in strings {
+            out.extend((s.len() as u32).to_le_bytes());
+            out.extend(s.as_bytes());
+        }
+    }
+
+    /// Returns all strings of the item in the order they were stored
+    pub fn strings(&self) -> impl Iterator<Item = &str> {
+        let mut data = self.strings;
+        std::iter::from_fn(move || {
+            let len = u32::from_le_bytes(data.get(0..4)?.try_into().unwrap()) as usize;
+            let s = data.get(4..4 + len)?;
+            data = &data[4 + len..];
+            Some(std::str::from_utf8(s).unwrap_or_default())
+        })
+    }
+
+    /// Returns the main string of the item. This is the word for basic items and the
+    /// kana reading for Japanese items
+    #[inline]
+    pub fn primary(&self) -> &str {
+        self.strings().next().unwrap_or_default()
+    }
+
+    /// Returns the kanji reading of a Japanese item
+    #[inline]
+    pub fn kanji(&self) -> Option<&str> {
+        if self.kind != IndexKind::Japanese {
+            return None;
+        }
+        self.strings().nth(1).filter(|i| !i.is_empty())
+    }
+
+    /// Returns the furigana of a Japanese item as (surface, reading) pairs
+    pub fn furigana(&self) -> Option<Vec<(String, String)>> {
+        if self.kind != IndexKind::Japanese {
+            return None;
+        }
+        let encoded = self.strings().nth(2).filter(|i| !i.is_empty())?;
+        let parts: Vec<&str> = encoded.split(FURIGANA_SEPARATOR).collect();
+        let pairs = parts
+            .chunks_exact(2)
+            .map(|i| (i[0].to_string(), i[1].to_string()))
+            .collect();
+        Some(pairs)
+    }
+
+    /// Encodes furigana to be stored as string of a Japanese item
+    pub(crate) fn encode_furigana(furigana: &[(String, String)]) -> String {
+        let mut out = String::new();
+        for (surface, reading) in furigana {
+            if !out.is_empty() {
+                out.push(FURIGANA_SEPARATOR);
+            }
+            out.push_str(surface);
+            out.push(FURIGANA_SEPARATOR);
+            out.push_str(reading);
+        }
+        out
+    }
+
+    /// Get the index item's frequency.
+    #[inline]
+    pub fn frequency(&self) -> f64 {
+        self.frequency
+    }
+}
+
+impl<'a> ToOutput for MmapItem<'a> {
+    #[inline]
+    fn to_output(&self) -> Output {
+        let mut output = Output::new(
+            self.primary().to_string(),
+            self.kanji().map(|i| i.to_string()),
+        );
+        output.furigana = self.furigana();
+        output
+    }
+}
+
+impl<'a> IndexItem for MmapItem<'a> {
+    #[inline]
+    fn frequency(&self) -> f64 {
+        self.frequency
+    }
+
+    #[inline]
+    fn str_relevance(&self, query: &str) -> u16 {
+        match self.kind {
+            IndexKind::Japanese => {
+                let alternative = self.strings().skip(3);
+                japanese::item::reading_relevance(self.primary(), self.kanji(), alternative, query)
+            }
+            _ => basic::item::word_relevance(self.primary(), query),
+        }
+    }
+
+    #[inline]
+    fn into_engine_item(&self) -> EngineItem {
+        EngineItem::new(self, 0)
+    }
+
+    #[inline]
+    fn terms(&self) -> Vec<&str> {
+        let mut out = vec![self.primary()];
+        if let Some(kanji) = self.kanji() {
+            out.push(kanji);
+        }
+        out
+    }
+
+    #[inline]
+    fn word_id(&self) -> u32 {
+        self.word_id
+    }
+
+    #[inline]
+    fn priority(&self) -> Priority {
+        self.priority
+    }
+}
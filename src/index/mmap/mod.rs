@@ -0,0 +1,408 @@
+pub mod item;
+pub mod writer;
+
+pub use item::MmapItem;
+
+use item::RECORD_HEADER_LEN;
+pub use writer::MmapWriter;
+
+use std::{
+    fs::File,
+    io::{self, ErrorKind},
+    ops::Range,
+    path::Path,
+    sync::OnceLock,
+};
+
+use memmap2::Mmap;
+
+use super::{
+    basic,
+    japanese::typo::KanaCosts,
+    prefix_tree::{Levenshtein, TreeView, EDIT_COST},
+    IndexItem, IndexKind, SuggestionIndex, WordIdMap,
+};
+use crate::{
+    normalize::{kana, Normalizer, NormalizerPipeline},
+    relevance::item::EngineItem,
+};
+
+pub(crate) const MAGIC: &[u8; 8] = b"ACMMAPIX";
+pub(crate) const VERSION: u32 = 1;
+pub(crate) const SECTION_COUNT: usize = 8;
+/// Magic, version, kind, flags, four padding bytes and an (offset, length) pair for each
+/// section
+pub(crate) const HEADER_LEN: usize = 24 + SECTION_COUNT * 16;
+/// Header flag of basic indexes which additionally hold their terms without diacritics
+pub(crate) const FLAG_FOLD_DIACRITICS: u32 = 1;
+
+/// Amount of items that get parsed at once
+const CHUNK_SIZE: usize = 1024;
+
+/// Sections of a memory mapped index file. Each section is aligned to 8 bytes
+#[derive(Clone, Copy)]
+pub(crate) enum Section {
+    Labels,
+    Children,
+    IdOffsets,
+    Ids,
+    MaxFreq,
+    ItemOffsets,
+    Items,
+    /// Bincode encoded `NormalizerPipeline`
+    Normalizer,
+}
+
+impl Section {
+    pub(crate) const ALL: [Section; SECTION_COUNT] = [
+        Section::Labels,
+        Section::Children,
+        Section::IdOffsets,
+        Section::Ids,
+        Section::MaxFreq,
+        Section::ItemOffsets,
+        Section::Items,
+        Section::Normalizer,
+    ];
+}
+
+/// Read only index that gets queried in place on a memory mapped file. Nothing but a few
+/// bytes per accessed item gets copied onto the heap, so opening an index is instant and
+/// multiple processes can share a single page cached file.
+///
+/// Files get written with `BasicIndex::write_mmap` or `JapaneseIndex::write_mmap`.
+/// Similar-terms, n-gram and kanji-align lookups are not supported.
+pub struct MmapIndex {
+    /// Lazily parsed items. Their strings borrow from `mmap`, so they get dropped first
+    /// explicitly in `Drop`, regardless of the field order
+    chunks: Box<[OnceLock<Box<[MmapItem<'static>]>>]>,
+    kind: IndexKind,
+    sections: [Range<usize>; SECTION_COUNT],
+    item_count: usize,
+    normalizer: NormalizerPipeline,
+    /// Whether terms were additionally indexed without diacritics
+    fold_diacritics: bool,
+    word_ids: WordIdMap,
+    mmap: Mmap,
+}
+
+impl MmapIndex {
+    /// Opens the index file at `path`
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let file = File::open(path)?;
+        // Safety: The file is expected to not get modified while it is mapped
+        let mmap = unsafe { Mmap::map(&file)? };
+        Self::from_mmap(mmap)
+    }
+
+    /// Creates a new MmapIndex from an existing memory map
+    pub fn from_mmap(mmap: Mmap) -> io::Result<Self> {
+        if !cfg!(target_endian = "little") {
+            return Err(invalid(
+                "memory mapped indexes require a little endian target",
+            ));
+        }
+
+        if mmap.len() < HEADER_LEN || &mmap[0..8] != MAGIC {
+            return Err(invalid("not a memory mapped index file"));
+        }
+
+        // Sections are read in place as numbers, so the mapping itself has to be aligned
+        if mmap.as_ptr() as usize % 8 != 0 {
+            return Err(invalid("not a memory mapped index file"));
+        }
+
+        let read_u32 = |pos: usize| u32::from_le_bytes(mmap[pos..pos + 4].try_into().unwrap());
+        let read_u64 = |pos: usize| u64::from_le_bytes(mmap[pos..pos + 8].try_into().unwrap());
+
+        if read_u32(8) != VERSION {
+            return Err(invalid("unsupported index file version"));
+        }
+
+        let kind = IndexKind::from_u32(read_u32(12))
+            .filter(|i| {
+                !matches!(
+                    i,
+                    IndexKind::Ngram | IndexKind::Kanji | IndexKind::LanguageModel
+                )
+            })
+            .ok_or_else(|| invalid("unsupported index kind"))?;
+        let fold_diacritics = read_u32(16) & FLAG_FOLD_DIACRITICS != 0;
+
+        let mut sections: [Range<usize>; SECTION_COUNT] = Default::default();
+        for (i, section) in sections.iter_mut().enumerate() {
+            let start = read_u64(24 + i * 16) as usize;
+            let len = read_u64(32 + i * 16) as usize;
+            if start % 8 != 0 || start.checked_add(len).map_or(true, |e| e > mmap.len()) {
+                return Err(invalid("corrupted section table"));
+            }
+            *section = start..start + len;
+        }
+
+        let element_size = |section: Section| match section {
+            Section::Labels | Section::Children | Section::IdOffsets | Section::Ids => 4,
+            Section::MaxFreq | Section::ItemOffsets => 8,
+            Section::Items | Section::Normalizer => 1,
+        };
+        for section in Section::ALL {
+            if sections[section as usize].len() % element_size(section) != 0 {
+                return Err(invalid("corrupted section table"));
+            }
+        }
+
+        let normalizer = &mmap[sections[Section::Normalizer as usize].clone()];
+        let normalizer = bincode::deserialize(normalizer)
+            .map_err(|_| invalid("corrupted normalizer section"))?;
+
+        let item_offsets = sections[Section::ItemOffsets as usize].len() / 8;
+        let item_count = item_offsets.saturating_sub(1);
+        let chunk_count = item_count.div_ceil(CHUNK_SIZE);
+
+        let index = Self {
+            chunks: (0..chunk_count).map(|_| OnceLock::new()).collect(),
+            kind,
+            sections,
+            item_count,
+            normalizer,
+            fold_diacritics,
+            word_ids: WordIdMap::default(),
+            mmap,
+        };
+        index.tree().validate().map_err(invalid)?;
+        index.validate_items()?;
+        Ok(index)
+    }
+
+    /// Checks that the item offsets are increasing, within the items section and that each
+    /// record is large enough to hold its fixed size part
+    fn validate_items(&self) -> io::Result<()> {
+        let offsets: &[u64] = self.array(Section::ItemOffsets);
+        let items_len = self.section(Section::Items).len() as u64;
+
+        if offsets.last().is_some_and(|i| *i > items_len) {
+            return Err(invalid("corrupted item offsets"));
+        }
+        let too_short = offsets
+            .windows(2)
+            .any(|w| w[1] < w[0] || w[1] - w[0] < RECORD_HEADER_LEN as u64);
+        if too_short {
+            return Err(invalid("corrupted item offsets"));
+        }
+        Ok(())
+    }
+
+    /// Returns the type of the mapped index
+    #[inline]
+    pub fn kind(&self) -> IndexKind {
+        self.kind
+    }
+
+    /// Returns the item with the given ID
+    #[inline]
+    pub fn get_item(&self, id: u32) -> Option<&MmapItem> {
+        let id = id as usize;
+        if id >= self.item_count {
+            return None;
+        }
+
+        let chunk = self.chunks[id / CHUNK_SIZE].get_or_init(|| self.parse_chunk(id / CHUNK_SIZE));
+        chunk.get(id % CHUNK_SIZE)
+    }
+
+    fn parse_chunk(&self, chunk: usize) -> Box<[MmapItem<'static>]> {
+        let offsets: &[u64] = self.array(Section::ItemOffsets);
+        let items = self.section(Section::Items);
+
+        // Safety: Items never outlive `self.mmap` since `Drop` drops them before it and they
+        // only get handed out borrowed from `self`. The mapped memory doesn't move with `Mmap`.
+        let items: &'static [u8] =
+            unsafe { std::slice::from_raw_parts(items.as_ptr(), items.len()) };
+
+        let start = chunk * CHUNK_SIZE;
+        let end = (start + CHUNK_SIZE).min(self.item_count);
+        // Offsets got validated on opening
+        (start..end)
+            .map(|i| {
+                let record = &items[offsets[i] as usize..offsets[i + 1] as usize];
+                MmapItem::parse(self.kind, record)
+            })
+            .collect()
+    }
+
+    /// Returns the key to look up `inp` with. Japanese indexes hold kana unified keys
+    #[inline]
+    fn lookup_key(&self, inp: &str) -> String {
+        match self.kind {
+            IndexKind::Japanese => kana::unify(inp),
+            _ => inp.to_string(),
+        }
+    }
+
+    /// Returns a view of the mapped prefix tree
+    #[inline]
+    fn tree(&self) -> TreeView {
+        TreeView {
+            labels: self.array(Section::Labels),
+            children: self.array(Section::Children),
+            id_offsets: self.array(Section::IdOffsets),
+            ids: self.array(Section::Ids),
+            max_freq: self.array(Section::MaxFreq),
+        }
+    }
+
+    #[inline]
+    fn frequency(&self, id: u32) -> f64 {
+        self.get_item(id).map(|i| i.frequency()).unwrap_or(0.0)
+    }
+
+    #[inline]
+    fn section(&self, section: Section) -> &[u8] {
+        &self.mmap[self.sections[section as usize].clone()]
+    }
+
+    #[inline]
+    fn array<T: Plain>(&self, section: Section) -> &[T] {
+        // Safety: Sections are 8 byte aligned and a multiple of the element size, which got
+        // checked on opening. `T` is only implemented for numbers, which are valid for every
+        // bit pattern
+        let (prefix, out, suffix) = unsafe { self.section(section).align_to::<T>() };
+        debug_assert!(prefix.is_empty() && suffix.is_empty());
+        out
+    }
+}
+
+impl Drop for MmapIndex {
+    #[inline]
+    fn drop(&mut self) {
+        // Items borrow from the mapped memory, so they have to be gone before it gets unmapped
+        self.chunks = Box::new([]);
+    }
+}
+
+impl SuggestionIndex for MmapIndex {
+    fn predictions(&self, inp: &str, limit: usize) -> Vec<EngineItem> {
+        let key = self.lookup_key(inp);
+        let freq = |id| self.frequency(id);
+        basic::prefix_lookup(self.tree(), &key, limit, self.fold_diacritics, freq)
+            .into_iter()
+            .filter_map(|id| self.get_word(id))
+            .collect()
+    }
+
+    fn exact(&self, inp: &str) -> Vec<EngineItem> {
+        let key = self.lookup_key(inp);
+        basic::exact_lookup(self.tree(), &key, self.fold_diacritics)
+            .into_iter()
+            .filter_map(|i| self.get_word(i))
+            .collect()
+    }
+
+    fn fuzzy_predictions(&self, inp: &str, limit: usize, max_dist: u32) -> Vec<EngineItem> {
+        let key = self.lookup_key(inp);
+        let max_cost = max_dist * EDIT_COST;
+        let freq = |id| self.frequency(id);
+
+        let found = match self.kind {
+            IndexKind::Japanese => self
+                .tree()
+                .fuzzy_top_k(&key, limit, max_cost, &KanaCosts, freq),
+            _ => self
+                .tree()
+                .fuzzy_top_k(&key, limit, max_cost, &Levenshtein, freq),
+        };
+
+        found
+            .into_iter()
+            .filter_map(|(id, dist)| Some(EngineItem::new(self.get_item(id)?, dist as u16)))
+            .collect()
+    }
+
+    #[inline]
+    fn get_word(&self, id: u32) -> Option<EngineItem> {
+        Some(self.get_item(id)?.into_engine_item())
+    }
+
+    fn word_by_id(&self, word_id: u32) -> Option<EngineItem> {
+        let ids = || (0..self.item_count as u32).filter_map(|i| Some(self.get_item(i)?.word_id()));
+        let pos = self.word_ids.position(word_id, ids)?;
+        self.get_word(pos)
+    }
+
+    #[inline]
+    fn len(&self) -> usize {
+        self.item_count
+    }
+
+    #[inline]
+    fn normalizer(&self) -> Option<&dyn Normalizer> {
+        Some(&self.normalizer)
+    }
+}
+
+/// Plain numbers that can be read directly from the mapped memory
+trait Plain {}
+impl Plain for u32 {}
+impl Plain for u64 {}
+impl Plain for f64 {}
+
+#[inline]
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use memmap2::MmapMut;
+
+    use super::*;
+    use crate::index::{
+        basic::BasicIndex,
+        test_util::{self, word_ids},
+    };
+
+    const WORDS: &[(&str, f64)] = &[("café", 0.5), ("über", 0.4), ("cafeteria", 0.3)];
+
+    fn mapped(index: &BasicIndex) -> MmapIndex {
+        let mut data = vec![];
+        index.write_mmap(&mut data).unwrap();
+        let mut mmap = MmapMut::map_anon(data.len()).unwrap();
+        mmap.copy_from_slice(&data);
+        MmapIndex::from_mmap(mmap.make_read_only().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_accent_insensitive() {
+        let index = test_util::basic_index(WORDS, |b| b.set_accent_insensitive(true));
+        let mapped = mapped(&index);
+
+        for query in ["café", "cafe", "cafè", "uber", "ueber"] {
+            assert_eq!(
+                word_ids(&mapped.exact(query)),
+                word_ids(&index.exact(query)),
+                "{query}"
+            );
+        }
+        assert_eq!(word_ids(&mapped.exact("cafè")), vec![0]);
+
+        for query in ["caf", "cafè", "cafet"] {
+            let found = mapped.predictions(query, 10);
+            let expected = index.predictions(query, 10);
+            assert_eq!(word_ids(&found), word_ids(&expected), "{query}");
+            for (a, b) in found.iter().zip(&expected) {
+                assert_eq!(
+                    a.inner().str_relevance(query),
+                    b.inner().str_relevance(query)
+                );
+            }
+        }
+        assert_eq!(word_ids(&mapped.predictions("cafè", 10)), vec![0, 2]);
+    }
+
+    #[test]
+    fn test_accent_sensitive() {
+        let index = test_util::basic_index(WORDS, |_| ());
+        let mapped = mapped(&index);
+        assert!(mapped.exact("cafè").is_empty());
+        assert_eq!(word_ids(&mapped.exact("café")), vec![0]);
+    }
+}
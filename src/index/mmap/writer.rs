@@ -0,0 +1,130 @@
+use std::io::{self, BufWriter, Write};
+
+use super::{
+    item::MmapItem, Section, FLAG_FOLD_DIACRITICS, HEADER_LEN, MAGIC, SECTION_COUNT, VERSION,
+};
+use crate::{
+    index::{prefix_tree::TreeView, IndexKind, Priority},
+    normalize::NormalizerPipeline,
+};
+
+/// Writes indexes in a format that can be memory mapped by `MmapIndex`
+pub struct MmapWriter {
+    kind: IndexKind,
+    item_offsets: Vec<u64>,
+    items: Vec<u8>,
+    normalizer: NormalizerPipeline,
+    fold_diacritics: bool,
+}
+
+impl MmapWriter {
+    /// Create a new MmapWriter for an index of type `kind`
+    pub fn new(kind: IndexKind) -> Self {
+        Self {
+            kind,
+            item_offsets: vec![],
+            items: vec![],
+            normalizer: NormalizerPipeline::default(),
+            fold_diacritics: false,
+        }
+    }
+
+    /// Sets the normalizer that was applied to the indexed terms
+    pub fn set_normalizer(&mut self, normalizer: NormalizerPipeline) {
+        self.normalizer = normalizer;
+    }
+
+    /// Marks that the terms were additionally indexed without diacritics, so the mapped
+    /// index looks up queries without diacritics as well
+    pub fn set_accent_insensitive(&mut self, enabled: bool) {
+        self.fold_diacritics = enabled;
+    }
+
+    /// Adds an item. Items have to be added in the order of their IDs
+    pub fn add_item(&mut self, word_id: u32, frequency: f64, priority: Priority, strings: &[&str]) {
+        self.item_offsets.push(self.items.len() as u64);
+        MmapItem::encode(word_id, frequency, priority, strings, &mut self.items);
+    }
+
+    /// Writes all added items together with the indexes `tree` into `out`
+    pub fn write<W: Write>(mut self, tree: TreeView, out: W) -> io::Result<()> {
+        self.item_offsets.push(self.items.len() as u64);
+        let normalizer = bincode::serialize(&self.normalizer)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+        let mut sections = [(0u64, 0u64); SECTION_COUNT];
+        let lengths = [
+            (Section::Labels, tree.labels.len() * 4),
+            (Section::Children, tree.children.len() * 4),
+            (Section::IdOffsets, tree.id_offsets.len() * 4),
+            (Section::Ids, tree.ids.len() * 4),
+            (Section::MaxFreq, tree.max_freq.len() * 8),
+            (Section::ItemOffsets, self.item_offsets.len() * 8),
+            (Section::Items, self.items.len()),
+            (Section::Normalizer, normalizer.len()),
+        ];
+        let mut offset = HEADER_LEN as u64;
+        for (section, len) in lengths {
+            sections[section as usize] = (offset, len as u64);
+            offset += padded(len) as u64;
+        }
+
+        let mut out = BufWriter::new(out);
+
+        out.write_all(MAGIC)?;
+        out.write_all(&VERSION.to_le_bytes())?;
+        out.write_all(&self.kind.to_u32().to_le_bytes())?;
+        let flags = if self.fold_diacritics {
+            FLAG_FOLD_DIACRITICS
+        } else {
+            0
+        };
+        out.write_all(&flags.to_le_bytes())?;
+        out.write_all(&[0u8; 4])?;
+        for (offset, len) in sections {
+            out.write_all(&offset.to_le_bytes())?;
+            out.write_all(&len.to_le_bytes())?;
+        }
+
+        write_u32s(&mut out, tree.labels)?;
+        write_u32s(&mut out, tree.children)?;
+        write_u32s(&mut out, tree.id_offsets)?;
+        write_u32s(&mut out, tree.ids)?;
+
+        for i in tree.max_freq {
+            out.write_all(&i.to_le_bytes())?;
+        }
+        pad(&mut out, tree.max_freq.len() * 8)?;
+
+        for i in &self.item_offsets {
+            out.write_all(&i.to_le_bytes())?;
+        }
+        pad(&mut out, self.item_offsets.len() * 8)?;
+
+        out.write_all(&self.items)?;
+        pad(&mut out, self.items.len())?;
+
+        out.write_all(&normalizer)?;
+        pad(&mut out, normalizer.len())?;
+
+        out.flush()
+    }
+}
+
+fn write_u32s<W: Write>(out: &mut W, data: &[u32]) -> io::Result<()> {
+    for i in data {
+        out.write_all(&i.to_le_bytes())?;
+    }
+    pad(out, data.len() * 4)
+}
+
+/// Pads a section of `len` bytes so the next one is aligned to 8 bytes
+#[inline]
+fn pad<W: Write>(out: &mut W, len: usize) -> io::Result<()> {
+    out.write_all(&[0u8; 8][..padded(len) - len])
+}
+
+#[inline]
+fn padded(len: usize) -> usize {
+    (len + 7) & !7
+}
@@ -1,15 +1,73 @@
 /// Generic index
 pub mod basic;
+/// Versioned index files
+pub mod file;
+/// Owned, shareable index handle
+pub mod handle;
 /// Index for Japanese terms
 pub mod japanese;
+/// Index for single kanji
+pub mod kanji;
+/// Bigram and trigram model for contextual and next-word suggestions
+pub mod language_model;
+/// Memory mapped, read only index
+pub mod mmap;
 /// Raw N-gram based index
 pub mod ngram;
 pub mod output;
+/// Mutable layer on top of an immutable index
+pub mod overlay;
+pub mod prefix_tree;
+/// Priority metadata of items
+pub mod priority;
 pub mod str_item;
+/// Fixtures shared by tests of the indexes
+#[cfg(test)]
+pub(crate) mod test_util;
 
 pub use output::Output;
+pub use priority::Priority;
 
-use crate::relevance::item::EngineItem;
+use crate::{normalize::Normalizer, relevance::item::EngineItem};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, sync::OnceLock};
+
+/// Type of an index
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum IndexKind {
+    Basic,
+    Japanese,
+    Ngram,
+    Kanji,
+    LanguageModel,
+}
+
+impl IndexKind {
+    /// Returns the numeric representation used in index files
+    #[inline]
+    pub fn to_u32(self) -> u32 {
+        match self {
+            IndexKind::Basic => 0,
+            IndexKind::Japanese => 1,
+            IndexKind::Ngram => 2,
+            IndexKind::Kanji => 3,
+            IndexKind::LanguageModel => 4,
+        }
+    }
+
+    /// Parses the numeric representation used in index files
+    #[inline]
+    pub fn from_u32(kind: u32) -> Option<Self> {
+        Some(match kind {
+            0 => IndexKind::Basic,
+            1 => IndexKind::Japanese,
+            2 => IndexKind::Ngram,
+            3 => IndexKind::Kanji,
+            4 => IndexKind::LanguageModel,
+            _ => return None,
+        })
+    }
+}
 
 /// Item in an index. Must be convertable to Output
 pub trait IndexItem: ToOutput + Send + Sync {
@@ -17,7 +75,13 @@ pub trait IndexItem: ToOutput + Send + Sync {
     fn word_id(&self) -> u32;
     fn str_relevance(&self, s: &str) -> u16;
     fn into_engine_item(&self) -> EngineItem;
-    fn terms(&self) -> Vec<&String>;
+    fn terms(&self) -> Vec<&str>;
+
+    /// Priority metadata of the item, used for ranking and filtering
+    #[inline]
+    fn priority(&self) -> Priority {
+        Priority::default()
+    }
 }
 
 /// Convert anything to `Output`
@@ -28,19 +92,45 @@ pub trait ToOutput {
 pub trait SuggestionIndex {
     fn predictions(&self, inp: &str, limit: usize) -> Vec<EngineItem>;
     fn exact(&self, inp: &str) -> Vec<EngineItem>;
+    /// Returns the item at position `id` of the index
     fn get_word(&self, id: u32) -> Option<EngineItem>;
 
+    /// Returns an item with the given word ID, eg. of a cached `SuggestionResult`. Word IDs
+    /// are set by the user and don't have to match the position used by `get_word`. Items
+    /// sharing a word ID return the first one. The default implementation scans all items
+    fn word_by_id(&self, word_id: u32) -> Option<EngineItem> {
+        (0..self.len() as u32)
+            .filter_map(|i| self.get_word(i))
+            .find(|i| i.inner().word_id() == word_id)
+    }
+
     #[inline]
     fn similar_terms(&self, _inp: &str, _limit: usize, _max_dist: u32) -> Vec<EngineItem> {
         vec![]
     }
 
+    /// Returns items with a key starting with a prefix that is within `max_dist` edits of
+    /// `inp`. The relevance of the returned items is set to this distance, where a single edit
+    /// costs `prefix_tree::EDIT_COST`. Indexes may weight likely typos below a full edit
+    #[inline]
+    fn fuzzy_predictions(&self, _inp: &str, _limit: usize, _max_dist: u32) -> Vec<EngineItem> {
+        vec![]
+    }
+
     fn len(&self) -> usize {
         0
     }
+
+    /// Returns the normalizer that was applied to the indexed terms. Queries have to be
+    /// normalized the same way
+    #[inline]
+    fn normalizer(&self) -> Option<&dyn Normalizer> {
+        None
+    }
 }
 
 pub trait KanjiReadingAlign {
+    /// Returns all items with an aligned spelling starting with `query`
     fn align_reading(&self, query: &str) -> Vec<EngineItem>;
 }
 
@@ -53,3 +143,27 @@ pub trait NGIndexable {
         term_limit: usize,
     ) -> Vec<EngineItem>;
 }
+
+/// Map from word IDs to the positions of the items in an index. Gets built on its first use
+/// and isn't serialized. Items sharing a word ID map to the first one
+#[derive(Default)]
+pub(crate) struct WordIdMap(OnceLock<HashMap<u32, u32>>);
+
+impl WordIdMap {
+    /// Returns the position of the item with `word_id`. `ids` has to return the word IDs of
+    /// all items ordered by their position
+    pub(crate) fn position<I, F>(&self, word_id: u32, ids: F) -> Option<u32>
+    where
+        I: Iterator<Item = u32>,
+        F: FnOnce() -> I,
+    {
+        let map = self.0.get_or_init(|| {
+            let mut map = HashMap::new();
+            for (pos, id) in ids().enumerate() {
+                map.entry(id).or_insert(pos as u32);
+            }
+            map
+        });
+        map.get(&word_id).copied()
+    }
+}
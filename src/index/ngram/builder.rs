@@ -2,12 +2,14 @@ use ngindex::builder::NGIndexBuilder;
 
 use super::item::Item;
 use super::NgramIndex;
+use crate::normalize::{Normalizer, NormalizerPipeline};
 use std::collections::HashMap;
 
 pub struct NgramIndexBuilder {
     out_terms: Vec<Item>,
     index_str: HashMap<String, Vec<usize>>,
     n: usize,
+    normalizer: NormalizerPipeline,
 }
 
 impl NgramIndexBuilder {
@@ -16,15 +18,22 @@ impl NgramIndexBuilder {
             out_terms: vec![],
             index_str: HashMap::new(),
             n,
+            normalizer: NormalizerPipeline::default(),
         }
     }
 
+    /// Sets the normalizer that gets applied to all inserted terms. The index keeps it to
+    /// normalize queries the same way
+    pub fn set_normalizer(&mut self, normalizer: NormalizerPipeline) {
+        self.normalizer = normalizer;
+    }
+
     pub fn insert<S: AsRef<str>>(&mut self, terms: &[S], item: Item) {
         let pos = self.out_terms.len();
         self.out_terms.push(item);
 
         for term in terms {
-            let term = term.as_ref().to_string();
+            let term = self.normalizer.normalize(term.as_ref());
             self.index_str.entry(term).or_default().push(pos);
         }
     }
@@ -41,6 +50,7 @@ impl NgramIndexBuilder {
             terms: self.out_terms,
             index: builder.build(),
             n: self.n,
+            normalizer: self.normalizer,
         }
     }
 
@@ -44,10 +44,7 @@ impl Item {
 impl ToOutput for Item {
     #[inline]
     fn to_output(&self) -> Output {
-        Output {
-            primary: self.word.clone(),
-            secondary: None,
-        }
+        Output::new(self.word.clone(), None)
     }
 }
 
@@ -75,8 +72,8 @@ impl super::super::IndexItem for Item {
     }
 
     #[inline]
-    fn terms(&self) -> Vec<&String> {
-        vec![&self.word]
+    fn terms(&self) -> Vec<&str> {
+        vec![self.word.as_str()]
     }
 
     #[inline]
@@ -4,8 +4,11 @@ pub mod item;
 pub use item::Item;
 use ngindex::{NGIndex, Vector};
 
-use super::{IndexItem, SuggestionIndex};
-use crate::relevance::item::EngineItem;
+use super::{file::IndexFile, IndexItem, IndexKind, SuggestionIndex};
+use crate::{
+    normalize::{Normalizer, NormalizerPipeline},
+    relevance::item::EngineItem,
+};
 use priority_container::PrioContainerMax;
 use serde::{Deserialize, Serialize};
 
@@ -16,6 +19,8 @@ pub struct NgramIndex {
     terms: Vec<Item>,
     index: NGIndex<Vec<u32>>,
     n: usize,
+    /// Normalizer that was applied to all terms
+    normalizer: NormalizerPipeline,
 }
 
 impl NgramIndex {
@@ -91,6 +96,25 @@ impl SuggestionIndex for NgramIndex {
     fn len(&self) -> usize {
         self.terms.len()
     }
+
+    #[inline]
+    fn normalizer(&self) -> Option<&dyn Normalizer> {
+        Some(&self.normalizer)
+    }
+}
+
+impl IndexFile for NgramIndex {
+    const KIND: IndexKind = IndexKind::Ngram;
+
+    #[inline]
+    fn term_count(&self) -> usize {
+        self.terms.len()
+    }
+
+    #[inline]
+    fn normalizer_config(&self) -> Option<String> {
+        Some(self.normalizer.config())
+    }
 }
 
 pub fn padded(word: &str, n: usize) -> String {
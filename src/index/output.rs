@@ -1,20 +1,32 @@
 use serde::{Deserialize, Serialize};
 
+use crate::normalize::{kana, romaji};
+
 /// A single suggestion in response
 #[derive(Serialize, Deserialize, PartialEq, Eq, Hash, Ord, PartialOrd, Clone)]
 pub struct Output {
     pub primary: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub secondary: Option<String>,
+    /// Reading of each part of `secondary` as (surface, reading) pairs. Kana parts have the
+    /// same surface and reading
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub furigana: Option<Vec<(String, String)>>,
+    /// Hepburn romaji of the reading. Only set on request, see `set_romaji`
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub romaji: Option<String>,
 }
 
 impl std::fmt::Debug for Output {
     #[inline]
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "OutputItem: {}", self.primary)?;
-        if let Some(sec) = &self.secondary {
+        if let Some(sec) = self.ruby().as_ref().or(self.secondary.as_ref()) {
             write!(f, " ({})", sec)?;
         }
+        if let Some(romaji) = &self.romaji {
+            write!(f, " [{}]", romaji)?;
+        }
         Ok(())
     }
 }
@@ -23,6 +35,34 @@ impl Output {
     /// Create a new SuggestionItem
     #[inline]
     pub fn new(primary: String, secondary: Option<String>) -> Self {
-        Self { primary, secondary }
+        Self {
+            primary,
+            secondary,
+            furigana: None,
+            romaji: None,
+        }
+    }
+
+    /// Sets `romaji` to the Hepburn romaji of `primary` if it is written in kana
+    pub fn set_romaji(&mut self) {
+        if !self.primary.is_empty() && self.primary.chars().all(kana::is_kana) {
+            self.romaji = Some(romaji::to_hepburn(&self.primary));
+        }
+    }
+
+    /// Renders `secondary` with its furigana in brackets, eg. 食[た]べる. Returns `None` if the
+    /// output has no furigana
+    pub fn ruby(&self) -> Option<String> {
+        let furigana = self.furigana.as_ref()?;
+        let mut out = String::new();
+        for (surface, reading) in furigana {
+            out.push_str(surface);
+            if surface != reading {
+                out.push('[');
+                out.push_str(reading);
+                out.push(']');
+            }
+        }
+        Some(out)
     }
 }
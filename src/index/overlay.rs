@@ -0,0 +1,659 @@
+use std::collections::{BTreeMap, HashSet};
+
+use priority_container::PrioContainerMax;
+
+use super::{
+    prefix_tree::{EditCosts, Levenshtein, EDIT_COST},
+    IndexItem, NGIndexable, Output, Priority, SuggestionIndex, ToOutput,
+};
+use crate::{
+    normalize::{kana, Normalizer},
+    relevance::{item::EngineItem, selection::SelectionLog},
+};
+
+/// Mutable layer on top of an immutable base index. Allows inserting and deleting items and
+/// changing their frequency without rebuilding the base. Items of the base that got deleted
+/// or changed are hidden by their word ID. Use `compact_with` to fold the changes into a new
+/// base once the delta grows too large.
+///
+/// Positions of delta items follow the ones of the base, so `get_word` returns them for IDs
+/// starting at the length of the base. Similar-terms lookups compare delta items by the
+/// eudex hashes of their keys, like `BasicIndex` does.
+pub struct OverlayIndex<B> {
+    base: B,
+    /// Inserted and changed items
+    items: Vec<OverlayItem>,
+    /// Normalized keys of the delta items
+    keys: BTreeMap<String, Vec<u32>>,
+    /// Word IDs of base items that got deleted or replaced by a delta item
+    shadowed: HashSet<u32>,
+}
+
+/// An item of the delta layer
+#[derive(Clone, Debug, PartialEq)]
+pub struct OverlayItem {
+    word_id: u32,
+    terms: Vec<String>,
+    /// Normalized and kana unified `terms`. Set when the item gets inserted
+    keys: Vec<String>,
+    output: Output,
+    /// Frequency of the item without `selection_boost`
+    frequency: f64,
+    /// Frequency gained from user selections, see `OverlayIndex::apply_selections`
+    selection_boost: f64,
+    priority: Priority,
+    removed: bool,
+}
+
+impl OverlayItem {
+    /// Create a new item which can be found by all `terms`
+    pub fn new<S: ToString>(word_id: u32, terms: &[S], output: Output, frequency: f64) -> Self {
+        Self {
+            word_id,
+            terms: terms.iter().map(|i| i.to_string()).collect(),
+            keys: vec![],
+            output,
+            frequency,
+            selection_boost: 0.0,
+            priority: Priority::default(),
+            removed: false,
+        }
+    }
+
+    /// Copies an item of any index. Only the items terms, output, frequency and priority are
+    /// kept, index specific data like separate kana and kanji readings gets lost
+    pub fn from_item(item: &dyn IndexItem) -> Self {
+        Self {
+            word_id: item.word_id(),
+            terms: item.terms().into_iter().map(|i| i.to_string()).collect(),
+            keys: vec![],
+            output: item.to_output(),
+            frequency: item.frequency(),
+            selection_boost: 0.0,
+            priority: item.priority(),
+            removed: false,
+        }
+    }
+
+    #[inline]
+    pub fn with_priority(mut self, priority: Priority) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    #[inline]
+    pub fn output(&self) -> &Output {
+        &self.output
+    }
+
+    /// Returns the frequency without the boost from user selections. Rebuilt bases should use
+    /// this so later calls of `apply_selections` don't add the boost a second time
+    #[inline]
+    pub fn corpus_frequency(&self) -> f64 {
+        self.frequency
+    }
+}
+
+impl<B: SuggestionIndex> OverlayIndex<B> {
+    /// Create a new overlay without any changes on top of `base`
+    pub fn new(base: B) -> Self {
+        Self {
+            base,
+            items: vec![],
+            keys: BTreeMap::new(),
+            shadowed: HashSet::new(),
+        }
+    }
+
+    /// Returns the underlying base index
+    #[inline]
+    pub fn base(&self) -> &B {
+        &self.base
+    }
+
+    /// Returns the amount of changes stored in the delta layer
+    #[inline]
+    pub fn delta_len(&self) -> usize {
+        self.items.len() + self.shadowed.len()
+    }
+
+    /// Inserts `item`. An existing item with the same word ID gets replaced
+    pub fn insert(&mut self, mut item: OverlayItem) {
+        self.delete(item.word_id);
+
+        let id = self.items.len() as u32;
+        item.keys = item.terms.iter().map(|i| self.key(i)).collect();
+        for key in &item.keys {
+            let ids = self.keys.entry(key.clone()).or_default();
+            if !ids.contains(&id) {
+                ids.push(id);
+            }
+        }
+        self.items.push(item);
+    }
+
+    /// Removes the item with the given word ID. Returns `false` if there was no such item
+    pub fn delete(&mut self, word_id: u32) -> bool {
+        let mut found = false;
+        for item in self.items.iter_mut().filter(|i| i.word_id == word_id) {
+            found |= !item.removed;
+            item.removed = true;
+        }
+
+        if self.base.word_by_id(word_id).is_some() {
+            found |= self.shadowed.insert(word_id);
+        }
+
+        found
+    }
+
+    /// Changes the frequency of the item with the given word ID. Returns `false` if there is
+    /// no such item
+    pub fn update_frequency(&mut self, word_id: u32, frequency: f64) -> bool {
+        if let Some(item) = self
+            .items
+            .iter_mut()
+            .find(|i| i.word_id == word_id && !i.removed)
+        {
+            item.frequency = frequency;
+            return true;
+        }
+
+        if self.shadowed.contains(&word_id) {
+            return false;
+        }
+
+        // Base items get replaced by a copy with the new frequency
+        let mut item = match self.base.word_by_id(word_id) {
+            Some(item) => OverlayItem::from_item(*item.inner()),
+            None => return false,
+        };
+        item.frequency = frequency;
+        self.insert(item);
+        true
+    }
+
+    /// Blends the selections recorded in `log` into the frequencies of the selected items.
+    /// The boost replaces the one of earlier calls, so this can be called periodically.
+    /// Returns the amount of updated items
+    pub fn apply_selections(&mut self, log: &SelectionLog) -> usize {
+        let mut updated = 0;
+        for word_id in log.word_ids() {
+            let boost = log.frequency_boost(word_id);
+
+            if let Some(item) = self
+                .items
+                .iter_mut()
+                .find(|i| i.word_id == word_id && !i.removed)
+            {
+                item.selection_boost = boost;
+                updated += 1;
+                continue;
+            }
+
+            if self.shadowed.contains(&word_id) {
+                continue;
+            }
+
+            // Base items get replaced by a copy once, later calls update the copy
+            let mut item = match self.base.word_by_id(word_id) {
+                Some(item) => OverlayItem::from_item(*item.inner()),
+                None => continue,
+            };
+            item.selection_boost = boost;
+            self.insert(item);
+            updated += 1;
+        }
+        updated
+    }
+
+    /// Builds a new base out of all items of the overlay and returns an overlay without
+    /// changes on top of it. `build` gets all items and has to build the new base, so it can
+    /// use the same builder settings as the old one.
+    ///
+    /// Base items are passed as `OverlayItem::from_item` copies, which only hold their terms
+    /// and rendered output. This only rebuilds an equivalent base for flat indexes like
+    /// `BasicIndex`. Indexes with richer items like `JapaneseIndex` should be rebuilt from
+    /// their source data instead, with the changes of the overlay applied to it.
+    pub fn compact_with<F>(self, build: F) -> Self
+    where
+        F: FnOnce(Vec<OverlayItem>) -> B,
+    {
+        let mut items: Vec<OverlayItem> = (0..)
+            .map_while(|id| self.base.get_word(id))
+            .filter(|i| !self.shadowed.contains(&i.inner().word_id()))
+            .map(|i| OverlayItem::from_item(*i.inner()))
+            .collect();
+        items.extend(self.items.into_iter().filter(|i| !i.removed));
+
+        Self::new(build(items))
+    }
+
+    /// Normalizes a term the same way the base normalizes queries
+    fn key(&self, term: &str) -> String {
+        let term = match self.base.normalizer() {
+            Some(normalizer) => normalizer.normalize(term),
+            None => term.to_string(),
+        };
+        kana::unify(&term)
+    }
+
+    #[inline]
+    fn is_visible(&self, item: &EngineItem) -> bool {
+        !self.shadowed.contains(&item.inner().word_id())
+    }
+
+    /// Live delta items with a key starting with `prefix`
+    fn delta_prefix<'s>(&'s self, prefix: &'s str) -> impl Iterator<Item = &'s OverlayItem> + 's {
+        self.keys
+            .range(prefix.to_string()..)
+            .take_while(move |(k, _)| k.starts_with(prefix))
+            .flat_map(|(_, ids)| ids.iter())
+            .map(move |i| &self.items[*i as usize])
+            .filter(|i| !i.removed)
+    }
+
+    /// Live delta items
+    #[inline]
+    fn delta_items(&self) -> impl Iterator<Item = &OverlayItem> {
+        self.items.iter().filter(|i| !i.removed)
+    }
+
+    /// Looks up items of the base and drops hidden ones. Requests enough items to make up for
+    /// all hidden ones
+    fn base_lookup<'s, F>(&'s self, limit: usize, lookup: F) -> Vec<EngineItem<'s>>
+    where
+        F: FnOnce(usize) -> Vec<EngineItem<'s>>,
+    {
+        let mut out = lookup(limit + self.shadowed.len());
+        out.retain(|i| self.is_visible(i));
+        out
+    }
+}
+
+impl<B: SuggestionIndex> SuggestionIndex for OverlayIndex<B> {
+    fn predictions(&self, inp: &str, limit: usize) -> Vec<EngineItem> {
+        let mut out = self.base_lookup(limit, |l| self.base.predictions(inp, l));
+
+        let mut seen = HashSet::new();
+        out.extend(
+            self.delta_prefix(&kana::unify(inp))
+                .filter(|i| seen.insert(i.word_id))
+                .map(|i| i.into_engine_item()),
+        );
+
+        out.sort_by(|a, b| b.inner().frequency().total_cmp(&a.inner().frequency()));
+        out.truncate(limit);
+        out
+    }
+
+    fn exact(&self, inp: &str) -> Vec<EngineItem> {
+        let mut out = self.base.exact(inp);
+        out.retain(|i| self.is_visible(i));
+
+        if let Some(ids) = self.keys.get(&kana::unify(inp)) {
+            out.extend(
+                ids.iter()
+                    .map(|i| &self.items[*i as usize])
+                    .filter(|i| !i.removed)
+                    .map(|i| i.into_engine_item()),
+            );
+        }
+        out
+    }
+
+    fn get_word(&self, id: u32) -> Option<EngineItem> {
+        let base_len = self.base.len() as u32;
+        if id >= base_len {
+            let item = self.items.get((id - base_len) as usize)?;
+            return (!item.removed).then(|| item.into_engine_item());
+        }
+        self.base.get_word(id).filter(|i| self.is_visible(i))
+    }
+
+    fn word_by_id(&self, word_id: u32) -> Option<EngineItem> {
+        if let Some(item) = self
+            .items
+            .iter()
+            .find(|i| i.word_id == word_id && !i.removed)
+        {
+            return Some(item.into_engine_item());
+        }
+        self.base.word_by_id(word_id).filter(|i| self.is_visible(i))
+    }
+
+    fn similar_terms(&self, inp: &str, limit: usize, max_dist: u32) -> Vec<EngineItem> {
+        let mut out = self.base_lookup(limit, |l| self.base.similar_terms(inp, l, max_dist));
+
+        let query = kana::unify(inp);
+        let first = query.chars().next();
+        if first.is_some() && query.len() <= 16 {
+            // can't build proper hashes with len() > 16
+            let query_hash = eudex::Hash::new(&query);
+            for item in self.delta_items() {
+                let dist = item
+                    .keys
+                    .iter()
+                    .filter(|k| k.len() <= 16 && k.chars().next() == first)
+                    .map(|k| (query_hash - eudex::Hash::new(k)).dist())
+                    .min();
+                if let Some(dist) = dist.filter(|d| *d <= max_dist) {
+                    out.push(EngineItem::new(item, dist as u16));
+                }
+            }
+        }
+
+        out.sort_by_key(|i| i.get_relevance());
+        out.truncate(limit);
+        out
+    }
+
+    fn fuzzy_predictions(&self, inp: &str, limit: usize, max_dist: u32) -> Vec<EngineItem> {
+        let mut out = self.base_lookup(limit, |l| self.base.fuzzy_predictions(inp, l, max_dist));
+
+        let query: Vec<char> = kana::unify(inp).chars().collect();
+        let max_cost = max_dist * EDIT_COST;
+        for item in self.delta_items() {
+            let cost = item
+                .keys
+                .iter()
+                .map(|k| prefix_cost(&query, k, &Levenshtein))
+                .min();
+            if let Some(cost) = cost.filter(|c| *c <= max_cost) {
+                out.push(EngineItem::new(item, cost as u16));
+            }
+        }
+
+        out.sort_by(|a, b| {
+            a.get_relevance()
+                .cmp(&b.get_relevance())
+                .then_with(|| b.inner().frequency().total_cmp(&a.inner().frequency()))
+        });
+        out.truncate(limit);
+        out
+    }
+
+    #[inline]
+    fn len(&self) -> usize {
+        let delta = self.delta_items().count();
+        (self.base.len() + delta).saturating_sub(self.shadowed.len())
+    }
+
+    #[inline]
+    fn normalizer(&self) -> Option<&dyn Normalizer> {
+        self.base.normalizer()
+    }
+}
+
+impl<B: SuggestionIndex + NGIndexable> NGIndexable for OverlayIndex<B> {
+    fn similar(
+        &self,
+        query: &str,
+        limit: usize,
+        q_weight: f32,
+        term_limit: usize,
+    ) -> Vec<EngineItem> {
+        let mut out =
+            self.base_lookup(limit, |l| self.base.similar(query, l, q_weight, term_limit));
+
+        // The delta is small enough to compare each item directly
+        let mut prio_queue = PrioContainerMax::new(limit);
+        let query = kana::unify(query);
+        for item in self.delta_items() {
+            let sim = item
+                .keys
+                .iter()
+                .map(|k| strsim::sorensen_dice(k, &query))
+                .fold(0.0, f64::max);
+            if sim > 0.0 {
+                prio_queue.insert(EngineItem::new(item, (sim * 1000.0) as u16));
+            }
+        }
+        out.extend(prio_queue.into_iter().map(|i| i.0));
+
+        out.sort_by(|a, b| b.get_relevance().cmp(&a.get_relevance()));
+        out.truncate(limit);
+        out
+    }
+}
+
+/// Returns the lowest cost of turning any prefix of `key` into `query`
+fn prefix_cost<C: EditCosts>(query: &[char], key: &str, costs: &C) -> u32 {
+    let mut row: Vec<u32> = Vec::with_capacity(query.len() + 1);
+    row.push(0);
+    for (j, c) in query.iter().enumerate() {
+        row.push(row[j] + costs.deletion(*c));
+    }
+
+    let mut best = row[query.len()];
+    let mut next = Vec::with_capacity(row.len());
+    for label in key.chars() {
+        let ins_cost = costs.insertion(label);
+        next.clear();
+        next.push(row[0] + ins_cost);
+        for j in 1..row.len() {
+            let q = query[j - 1];
+            let sub_cost = if q == label {
+                0
+            } else {
+                costs.substitution(q, label)
+            };
+            let val = (row[j] + ins_cost)
+                .min(next[j - 1] + costs.deletion(q))
+                .min(row[j - 1] + sub_cost);
+            next.push(val);
+        }
+        best = best.min(next[query.len()]);
+        std::mem::swap(&mut row, &mut next);
+    }
+    best
+}
+
+impl ToOutput for OverlayItem {
+    #[inline]
+    fn to_output(&self) -> Output {
+        self.output.clone()
+    }
+}
+
+impl IndexItem for OverlayItem {
+    #[inline]
+    fn frequency(&self) -> f64 {
+        (self.frequency + self.selection_boost).min(1.0)
+    }
+
+    #[inline]
+    fn word_id(&self) -> u32 {
+        self.word_id
+    }
+
+    /// Compares the normalized keys of the item with the normalized `query`
+    fn str_relevance(&self, query: &str) -> u16 {
+        let query = kana::unify(query);
+        let query = query.as_str();
+        self.keys
+            .iter()
+            .map(|term| {
+                if term.starts_with(query) && !term.is_empty() {
+                    let normalized = 1.0 - (query.len() as f32 / term.len() as f32);
+                    1000 - (normalized * 1000.0) as u16
+                } else {
+                    let sim = strsim::normalized_levenshtein(term, query);
+                    ((sim * 1000.0) as u16).saturating_sub(300)
+                }
+            })
+            .max()
+            .unwrap_or(0)
+    }
+
+    #[inline]
+    fn into_engine_item(&self) -> EngineItem {
+        EngineItem::new(self, 0)
+    }
+
+    #[inline]
+    fn terms(&self) -> Vec<&str> {
+        self.terms.iter().map(|i| i.as_str()).collect()
+    }
+
+    #[inline]
+    fn priority(&self) -> Priority {
+        self.priority
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        index::{basic::BasicIndex, test_util},
+        normalize::NormalizerPipeline,
+    };
+
+    const WORDS: &[(&str, f64)] = &[("apple", 0.5), ("apricot", 0.3), ("banana", 0.4)];
+
+    fn overlay() -> OverlayIndex<BasicIndex> {
+        let base = test_util::basic_index(WORDS, |b| b.set_normalizer(NormalizerPipeline::basic()));
+        OverlayIndex::new(base)
+    }
+
+    fn item(word_id: u32, term: &str, frequency: f64) -> OverlayItem {
+        let output = Output::new(term.to_string(), None);
+        OverlayItem::new(word_id, &[term], output, frequency)
+    }
+
+    fn ids(items: Vec<EngineItem>) -> Vec<u32> {
+        test_util::word_ids(&items)
+    }
+
+    #[test]
+    fn test_delete_shadows_base() {
+        let mut index = overlay();
+        assert!(index.delete(0));
+        assert!(!index.delete(0));
+
+        assert_eq!(ids(index.predictions("ap", 10)), vec![1]);
+        assert!(index.exact("apple").is_empty());
+        assert!(index.get_word(0).is_none());
+        assert!(index.word_by_id(0).is_none());
+        assert_eq!(index.len(), 2);
+    }
+
+    #[test]
+    fn test_word_by_id() {
+        let mut index = overlay();
+        index.insert(item(0, "apple", 0.9));
+        index.insert(item(10, "avocado", 0.2));
+
+        let changed = index.word_by_id(0).unwrap();
+        assert_eq!(changed.inner().frequency(), 0.9);
+        assert_eq!(changed.inner().to_output().primary, "apple");
+        assert_eq!(index.word_by_id(10).unwrap().inner().word_id(), 10);
+        assert_eq!(index.word_by_id(2).unwrap().inner().word_id(), 2);
+        assert!(index.word_by_id(99).is_none());
+    }
+
+    #[test]
+    fn test_lookup_skips_shadowed() {
+        let mut index = overlay();
+        assert!(index.delete(0));
+        assert!(index.delete(2));
+
+        assert_eq!(ids(index.predictions("", 1)), vec![1]);
+    }
+
+    #[test]
+    fn test_get_word_delta() {
+        let mut index = overlay();
+        index.insert(item(10, "avocado", 0.2));
+        index.insert(item(0, "apple", 0.9));
+
+        assert!(index.get_word(0).is_none());
+        assert_eq!(index.get_word(1).unwrap().inner().word_id(), 1);
+        assert_eq!(index.get_word(3).unwrap().inner().word_id(), 10);
+        assert_eq!(index.get_word(4).unwrap().inner().word_id(), 0);
+        assert!(index.get_word(5).is_none());
+
+        assert!(index.delete(10));
+        assert!(index.get_word(3).is_none());
+    }
+
+    #[test]
+    fn test_fuzzy_delta() {
+        let mut index = overlay();
+        index.insert(item(10, "avocado", 0.2));
+        assert!(index.delete(1));
+
+        assert_eq!(ids(index.fuzzy_predictions("avx", 10, 1)), vec![10]);
+        assert_eq!(ids(index.fuzzy_predictions("apr", 10, 1)), vec![0]);
+    }
+
+    #[test]
+    fn test_similar_terms_delta() {
+        let mut index = overlay();
+        index.insert(item(10, "avocado", 0.2));
+        assert!(index.delete(0));
+
+        let found = ids(index.similar_terms("avocado", 10, u32::MAX));
+        assert_eq!(found[0], 10);
+        assert!(!found.contains(&0));
+    }
+
+    #[test]
+    fn test_insert_replaces_base() {
+        let mut index = overlay();
+        index.insert(item(0, "apple", 0.9));
+
+        let exact = index.exact("apple");
+        assert_eq!(ids(exact.clone()), vec![0]);
+        assert_eq!(exact[0].inner().frequency(), 0.9);
+        assert_eq!(ids(index.predictions("ap", 10)), vec![0, 1]);
+        assert_eq!(index.len(), 3);
+    }
+
+    #[test]
+    fn test_delta_keys_normalized() {
+        let mut index = overlay();
+        index.insert(item(10, "Avocado", 0.2));
+
+        assert_eq!(ids(index.predictions("avo", 10)), vec![10]);
+        assert_eq!(ids(index.exact("avocado")), vec![10]);
+        let found = index.exact("avocado");
+        assert_eq!(found[0].inner().str_relevance("avocado"), 1000);
+        assert_eq!(index.len(), 4);
+
+        assert!(index.delete(10));
+        assert!(index.predictions("avo", 10).is_empty());
+        assert_eq!(index.len(), 3);
+    }
+
+    #[test]
+    fn test_update_frequency() {
+        let mut index = overlay();
+        assert!(index.update_frequency(2, 0.95));
+        assert!(!index.update_frequency(99, 0.1));
+
+        let found = index.predictions("b", 10);
+        assert_eq!(ids(found.clone()), vec![2]);
+        assert_eq!(found[0].inner().frequency(), 0.95);
+        assert_eq!(ids(index.predictions("", 10))[0], 2);
+    }
+
+    #[test]
+    fn test_selections_dont_compound() {
+        let mut index = overlay();
+        let log = SelectionLog::new();
+        log.record("apr", 1);
+
+        assert_eq!(index.apply_selections(&log), 1);
+        let first = index.exact("apricot")[0].inner().frequency();
+        let delta_len = index.delta_len();
+
+        assert_eq!(index.apply_selections(&log), 1);
+        let second = index.exact("apricot")[0].inner().frequency();
+
+        assert!(first > 0.3);
+        assert!((first - second).abs() < 1e-9);
+        assert_eq!(index.delta_len(), delta_len);
+    }
+}
@@ -0,0 +1,586 @@
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, BinaryHeap, HashMap, HashSet, VecDeque},
+};
+
+use serde::{Deserialize, Serialize};
+
+/// Cost of a single edit in distances of fuzzy lookups. Allows `EditCosts` to weight likely
+/// typos below a full edit
+pub const EDIT_COST: u32 = 100;
+
+/// Costs of the edit operations of fuzzy lookups
+pub trait EditCosts {
+    /// Cost of typing `query` instead of `key`
+    fn substitution(&self, query: char, key: char) -> u32;
+    /// Cost of a character of the key missing in the query
+    fn insertion(&self, key: char) -> u32;
+    /// Cost of an additional character in the query
+    fn deletion(&self, query: char) -> u32;
+}
+
+/// Plain Levenshtein distance where each edit costs `EDIT_COST`
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Levenshtein;
+
+impl EditCosts for Levenshtein {
+    #[inline]
+    fn substitution(&self, _query: char, _key: char) -> u32 {
+        EDIT_COST
+    }
+
+    #[inline]
+    fn insertion(&self, _key: char) -> u32 {
+        EDIT_COST
+    }
+
+    #[inline]
+    fn deletion(&self, _query: char) -> u32 {
+        EDIT_COST
+    }
+}
+
+/// Compact character tree over all keys of an index. Each node stores the highest frequency
+/// of all items within its subtree, which allows finding the top-k completions of a prefix
+/// with a best-first search instead of walking every item sharing this prefix.
+#[derive(Serialize, Deserialize)]
+pub struct PrefixTree {
+    /// Character leading into a node. Children of a node are stored consecutively and sorted
+    labels: Vec<u32>,
+    /// Children of node `n` are the nodes `children[n]..children[n + 1]`
+    children: Vec<u32>,
+    /// Item IDs of node `n` are `ids[id_offsets[n]..id_offsets[n + 1]]`
+    id_offsets: Vec<u32>,
+    ids: Vec<u32>,
+    /// Highest item frequency within the subtree of a node
+    max_freq: Vec<f64>,
+}
+
+impl PrefixTree {
+    /// Returns a borrowed view of the tree to run lookups on
+    #[inline]
+    pub fn view(&self) -> TreeView<'_> {
+        TreeView {
+            labels: &self.labels,
+            children: &self.children,
+            id_offsets: &self.id_offsets,
+            ids: &self.ids,
+            max_freq: &self.max_freq,
+        }
+    }
+}
+
+/// Borrowed `PrefixTree`. Lookups only need the raw arrays of a tree, which allows running
+/// them directly on trees that aren't owned, eg. memory mapped ones.
+#[derive(Clone, Copy)]
+pub struct TreeView<'a> {
+    pub(crate) labels: &'a [u32],
+    pub(crate) children: &'a [u32],
+    pub(crate) id_offsets: &'a [u32],
+    pub(crate) ids: &'a [u32],
+    pub(crate) max_freq: &'a [f64],
+}
+
+impl<'a> TreeView<'a> {
+    /// ID of the root node
+    pub const ROOT: u32 = 0;
+
+    /// Returns the node reached by walking `prefix` from the root
+    pub fn find(&self, prefix: &str) -> Option<u32> {
+        prefix
+            .chars()
+            .try_fold(Self::ROOT, |node, c| self.child(node, c))
+    }
+
+    /// Returns the child of `node` that is reached with `c`
+    #[inline]
+    pub fn child(&self, node: u32, c: char) -> Option<u32> {
+        let start = self.children[node as usize] as usize;
+        let end = self.children[node as usize + 1] as usize;
+        let pos = self.labels[start..end].binary_search(&(c as u32)).ok()?;
+        Some((start + pos) as u32)
+    }
+
+    /// Returns the IDs of all items whose key ends at `node`
+    #[inline]
+    pub fn node_ids(&self, node: u32) -> &'a [u32] {
+        let start = self.id_offsets[node as usize] as usize;
+        let end = self.id_offsets[node as usize + 1] as usize;
+        &self.ids[start..end]
+    }
+
+    /// Returns the IDs of all items with a key starting with `prefix`. Items with multiple
+    /// matching keys are returned once per key
+    pub fn prefix_ids(&self, prefix: &str) -> impl Iterator<Item = u32> + 'a {
+        let view = *self;
+        let mut stack: Vec<u32> = self.find(prefix).into_iter().collect();
+        std::iter::from_fn(move || {
+            let node = stack.pop()?;
+            stack.extend(view.children[node as usize]..view.children[node as usize + 1]);
+            Some(view.node_ids(node))
+        })
+        .flatten()
+        .copied()
+    }
+
+    /// Returns the IDs of the `k` most frequent items with a key starting with `prefix`,
+    /// ordered by frequency descending. `freq` has to return the frequency of an item ID.
+    pub fn top_k<F>(&self, prefix: &str, k: usize, freq: F) -> Vec<u32>
+    where
+        F: Fn(u32) -> f64,
+    {
+        match self.find(prefix) {
+            Some(node) => self.top_k_node(node, k, freq),
+            None => vec![],
+        }
+    }
+
+    /// Same as `top_k` but starts at a given node
+    pub fn top_k_node<F>(&self, node: u32, k: usize, freq: F) -> Vec<u32>
+    where
+        F: Fn(u32) -> f64,
+    {
+        let mut out = Vec::with_capacity(k);
+        let mut seen = HashSet::with_capacity(k);
+
+        let mut heap = BinaryHeap::new();
+        heap.push(Candidate::node(node, self.max_freq[node as usize]));
+
+        // Node scores are an upper bound for all items in their subtree, so items get
+        // popped strictly in descending frequency order
+        while let Some(candidate) = heap.pop() {
+            if out.len() >= k {
+                break;
+            }
+
+            if !candidate.is_node {
+                if seen.insert(candidate.id) {
+                    out.push(candidate.id);
+                }
+                continue;
+            }
+
+            let node = candidate.id;
+            for id in self.node_ids(node) {
+                heap.push(Candidate::item(*id, freq(*id)));
+            }
+
+            let start = self.children[node as usize];
+            let end = self.children[node as usize + 1];
+            for child in start..end {
+                heap.push(Candidate::node(child, self.max_freq[child as usize]));
+            }
+        }
+
+        out
+    }
+
+    /// Returns up to `k` item IDs with a key starting with a prefix that can be turned into
+    /// `query` for at most `max_cost`, together with this cost. Items are ordered by cost
+    /// ascending and frequency descending.
+    pub fn fuzzy_top_k<C, F>(
+        &self,
+        query: &str,
+        k: usize,
+        max_cost: u32,
+        costs: &C,
+        freq: F,
+    ) -> Vec<(u32, u32)>
+    where
+        C: EditCosts,
+        F: Fn(u32) -> f64,
+    {
+        let mut found: HashMap<u32, u32> = HashMap::with_capacity(k);
+
+        for (node, dist) in self.fuzzy_nodes(query, max_cost, costs) {
+            for id in self.top_k_node(node, k, &freq) {
+                let entry = found.entry(id).or_insert(dist);
+                *entry = (*entry).min(dist);
+            }
+        }
+
+        let mut out: Vec<_> = found.into_iter().collect();
+        out.sort_by(|a, b| a.1.cmp(&b.1).then_with(|| freq(b.0).total_cmp(&freq(a.0))));
+        out.truncate(k);
+        out
+    }
+
+    /// Returns all nodes whose path from the root can be turned into `query` for at most
+    /// `max_cost`, together with this cost. Nodes within the subtree of a found node only get
+    /// returned if they are closer to `query` than the found node itself.
+    pub fn fuzzy_nodes<C: EditCosts>(
+        &self,
+        query: &str,
+        max_cost: u32,
+        costs: &C,
+    ) -> Vec<(u32, u32)> {
+        let query: Vec<char> = query.chars().collect();
+        let mut row: Vec<u32> = Vec::with_capacity(query.len() + 1);
+        row.push(0);
+        for (j, c) in query.iter().enumerate() {
+            row.push(row[j] + costs.deletion(*c));
+        }
+
+        let mut out = vec![];
+        let mut best = u32::MAX;
+        if row[query.len()] <= max_cost {
+            best = row[query.len()];
+            out.push((Self::ROOT, best));
+        }
+
+        self.fuzzy_walk(Self::ROOT, &query, &row, max_cost, costs, best, &mut out);
+        out
+    }
+
+    /// Walks the children of `node` while updating the weighted Levenshtein automaton's state
+    /// `row`
+    #[allow(clippy::too_many_arguments)]
+    fn fuzzy_walk<C: EditCosts>(
+        &self,
+        node: u32,
+        query: &[char],
+        row: &[u32],
+        max_cost: u32,
+        costs: &C,
+        best: u32,
+        out: &mut Vec<(u32, u32)>,
+    ) {
+        let start = self.children[node as usize];
+        let end = self.children[node as usize + 1];
+
+        let mut next = Vec::with_capacity(row.len());
+
+        for child in start..end {
+            let label = char::from_u32(self.labels[child as usize]).unwrap_or_default();
+            let ins_cost = costs.insertion(label);
+
+            next.clear();
+            next.push(row[0] + ins_cost);
+            for j in 1..row.len() {
+                let q = query[j - 1];
+                let sub_cost = if q == label {
+                    0
+                } else {
+                    costs.substitution(q, label)
+                };
+                let val = (row[j] + ins_cost)
+                    .min(next[j - 1] + costs.deletion(q))
+                    .min(row[j - 1] + sub_cost);
+                next.push(val);
+            }
+
+            let mut child_best = best;
+            let dist = next[query.len()];
+            if dist <= max_cost && dist < best {
+                out.push((child, dist));
+                child_best = dist;
+            }
+
+            // The distance of all nodes below can't be smaller than the smallest value of the row
+            let min = next.iter().copied().min().unwrap_or(0);
+            if min <= max_cost && min < child_best {
+                self.fuzzy_walk(child, query, &next, max_cost, costs, child_best, out);
+            }
+        }
+    }
+
+    /// Returns the amount of nodes in the tree
+    #[inline]
+    pub fn node_count(&self) -> usize {
+        self.labels.len()
+    }
+
+    /// Checks that the arrays form a valid tree, so lookups can't index out of bounds or loop
+    /// forever. Children always have to come after their parent like in built trees
+    pub(crate) fn validate(&self) -> Result<(), &'static str> {
+        let nodes = self.labels.len();
+        if nodes == 0
+            || self.children.len() != nodes + 1
+            || self.id_offsets.len() != nodes + 1
+            || self.max_freq.len() != nodes
+        {
+            return Err("prefix tree arrays have mismatching lengths");
+        }
+
+        for (node, w) in self.children.windows(2).enumerate() {
+            if w[0] > w[1] || (w[0] != w[1] && (w[0] as usize) <= node) {
+                return Err("invalid prefix tree child range");
+            }
+        }
+        if self.children[nodes] as usize != nodes {
+            return Err("invalid prefix tree child range");
+        }
+
+        if self.id_offsets[0] != 0
+            || self.id_offsets.windows(2).any(|w| w[0] > w[1])
+            || self.id_offsets[nodes] as usize != self.ids.len()
+        {
+            return Err("invalid prefix tree id offsets");
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for PrefixTree {
+    #[inline]
+    fn default() -> Self {
+        PrefixTreeBuilder::new().build(|_| 0.0)
+    }
+}
+
+/// Helper to build a new `PrefixTree`
+pub struct PrefixTreeBuilder {
+    nodes: Vec<TmpNode>,
+}
+
+#[derive(Default)]
+struct TmpNode {
+    children: BTreeMap<char, u32>,
+    ids: Vec<u32>,
+}
+
+impl PrefixTreeBuilder {
+    pub fn new() -> Self {
+        Self {
+            nodes: vec![TmpNode::default()],
+        }
+    }
+
+    /// Adds `key` to the tree, mapping to the item with the given ID
+    pub fn insert(&mut self, key: &str, id: u32) {
+        let mut node = 0;
+
+        for c in key.chars() {
+            node = match self.nodes[node].children.get(&c) {
+                Some(child) => *child as usize,
+                None => {
+                    let child = self.nodes.len();
+                    self.nodes.push(TmpNode::default());
+                    self.nodes[node].children.insert(c, child as u32);
+                    child
+                }
+            };
+        }
+
+        if !self.nodes[node].ids.contains(&id) {
+            self.nodes[node].ids.push(id);
+        }
+    }
+
+    /// Builds the `PrefixTree`. `freq` has to return the frequency of an item ID
+    pub fn build<F>(self, freq: F) -> PrefixTree
+    where
+        F: Fn(u32) -> f64,
+    {
+        let node_count = self.nodes.len();
+
+        // Children always get created after their parent so iterating in reverse order
+        // visits all children before their parent
+        let mut max_freq = vec![0.0f64; node_count];
+        for (pos, node) in self.nodes.iter().enumerate().rev() {
+            let own = node.ids.iter().map(|i| freq(*i)).fold(0.0, f64::max);
+            let children = node
+                .children
+                .values()
+                .map(|c| max_freq[*c as usize])
+                .fold(0.0, f64::max);
+            max_freq[pos] = own.max(children);
+        }
+
+        let mut tree = PrefixTree {
+            labels: Vec::with_capacity(node_count),
+            children: Vec::with_capacity(node_count + 1),
+            id_offsets: Vec::with_capacity(node_count + 1),
+            ids: vec![],
+            max_freq: Vec::with_capacity(node_count),
+        };
+
+        // Flatten in breadth-first order so that children of a node end up next to each other
+        let mut queue = VecDeque::with_capacity(node_count);
+        queue.push_back((0usize, 0u32));
+        let mut next_child = 1u32;
+
+        while let Some((pos, label)) = queue.pop_front() {
+            let node = &self.nodes[pos];
+
+            tree.labels.push(label);
+            tree.children.push(next_child);
+            tree.id_offsets.push(tree.ids.len() as u32);
+            tree.ids.extend(&node.ids);
+            tree.max_freq.push(max_freq[pos]);
+
+            for (c, child) in &node.children {
+                queue.push_back((*child as usize, *c as u32));
+            }
+            next_child += node.children.len() as u32;
+        }
+
+        tree.children.push(next_child);
+        tree.id_offsets.push(tree.ids.len() as u32);
+
+        tree
+    }
+}
+
+impl Default for PrefixTreeBuilder {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Entry of the best-first search queue
+struct Candidate {
+    id: u32,
+    score: f64,
+    is_node: bool,
+}
+
+impl Candidate {
+    #[inline]
+    fn node(id: u32, score: f64) -> Self {
+        Self {
+            id,
+            score,
+            is_node: true,
+        }
+    }
+
+    #[inline]
+    fn item(id: u32, score: f64) -> Self {
+        Self {
+            id,
+            score,
+            is_node: false,
+        }
+    }
+}
+
+impl PartialEq for Candidate {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Candidate {}
+
+impl PartialOrd for Candidate {
+    #[inline]
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Candidate {
+    #[inline]
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Prefer items over nodes with the same score to finish early
+        self.score
+            .total_cmp(&other.score)
+            .then_with(|| other.is_node.cmp(&self.is_node))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::index::test_util;
+
+    const WORDS: &[(&str, f64)] = &[
+        ("hello", 0.9),
+        ("help", 0.5),
+        ("helium", 0.2),
+        ("hero", 0.7),
+        ("her", 0.8),
+        ("world", 0.6),
+        ("word", 0.4),
+        ("work", 0.3),
+        ("worm", 0.1),
+    ];
+
+    fn freq(id: u32) -> f64 {
+        WORDS[id as usize].1
+    }
+
+    fn build() -> PrefixTree {
+        test_util::prefix_tree(WORDS.iter().map(|i| i.0), freq)
+    }
+
+    fn brute_force(prefix: &str, k: usize) -> Vec<u32> {
+        let mut ids: Vec<u32> = (0..WORDS.len() as u32)
+            .filter(|i| WORDS[*i as usize].0.starts_with(prefix))
+            .collect();
+        ids.sort_by(|a, b| freq(*b).total_cmp(&freq(*a)));
+        ids.truncate(k);
+        ids
+    }
+
+    #[test]
+    fn test_top_k_matches_brute_force() {
+        let tree = build();
+        let view = tree.view();
+        for prefix in [
+            "", "h", "he", "hel", "her", "w", "wor", "worl", "x", "hello!",
+        ] {
+            for k in 0..=WORDS.len() + 1 {
+                let found = view.top_k(prefix, k, freq);
+                assert_eq!(found, brute_force(prefix, k), "prefix {prefix:?}, k {k}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_exact_and_prefix_ids() {
+        let tree = build();
+        let view = tree.view();
+
+        let her = view.find("her").unwrap();
+        assert_eq!(view.node_ids(her), &[4]);
+        assert!(view.node_ids(view.find("he").unwrap()).is_empty());
+        assert!(view.find("hex").is_none());
+
+        let mut ids: Vec<u32> = view.prefix_ids("wor").collect();
+        ids.sort_unstable();
+        assert_eq!(ids, vec![5, 6, 7, 8]);
+        assert_eq!(view.prefix_ids("").count(), WORDS.len());
+        assert_eq!(view.prefix_ids("x").count(), 0);
+    }
+
+    #[test]
+    fn test_fuzzy_top_k() {
+        let tree = build();
+        let view = tree.view();
+
+        // Prefixes of a key match without cost
+        let found = view.fuzzy_top_k("hel", 10, EDIT_COST, &Levenshtein, freq);
+        assert!(found.contains(&(0, 0)));
+
+        // "wrld" -> "world" needs a single insertion
+        let found = view.fuzzy_top_k("wrld", 10, EDIT_COST, &Levenshtein, freq);
+        assert!(found.contains(&(5, EDIT_COST)));
+        assert!(found.iter().all(|i| i.1 <= EDIT_COST));
+
+        let found = view.fuzzy_top_k("wrld", 10, 0, &Levenshtein, freq);
+        assert!(found.is_empty());
+    }
+
+    #[test]
+    fn test_fuzzy_results_ordered() {
+        let tree = build();
+        let found = tree
+            .view()
+            .fuzzy_top_k("hel", 10, 2 * EDIT_COST, &Levenshtein, freq);
+        for pair in found.windows(2) {
+            let (a, b) = (pair[0], pair[1]);
+            assert!(a.1 < b.1 || (a.1 == b.1 && freq(a.0) >= freq(b.0)));
+        }
+    }
+
+    #[test]
+    fn test_built_tree_is_valid() {
+        assert!(build().view().validate().is_ok());
+        assert!(PrefixTree::default().view().validate().is_ok());
+    }
+}
@@ -0,0 +1,129 @@
+use std::ops::RangeInclusive;
+
+use serde::{Deserialize, Serialize};
+
+/// Priority metadata of a dictionary entry. Used as a ranking feature for entries without
+/// (or in addition to) corpus frequencies
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Priority {
+    /// Whether the entry is marked as common
+    pub common: bool,
+    /// JLPT level from 5 (N5, easiest) to 1 (N1)
+    pub jlpt: Option<u8>,
+    /// Rank of the entry in a news corpus. 1 is the most frequent word
+    pub news_rank: Option<u32>,
+}
+
+impl Priority {
+    /// Create a new Priority without any data
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[inline]
+    pub fn with_common(mut self, common: bool) -> Self {
+        self.common = common;
+        self
+    }
+
+    #[inline]
+    pub fn with_jlpt(mut self, jlpt: u8) -> Self {
+        self.jlpt = Some(jlpt);
+        self
+    }
+
+    #[inline]
+    pub fn with_news_rank(mut self, news_rank: u32) -> Self {
+        self.news_rank = Some(news_rank);
+        self
+    }
+
+    /// Returns `true` if no priority data is set
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Combines all priority data into a single value between 0 and 1. The highest value of
+    /// the common flag (0.6), JLPT level (0.3 for N1 up to 0.7 for N5) and news rank (1 for
+    /// rank 1, halved every 2000 ranks) is used
+    pub fn score(&self) -> f64 {
+        let common = if self.common { 0.6 } else { 0.0 };
+        let jlpt = self
+            .jlpt
+            .map(|l| 0.2 + 0.1 * l.clamp(1, 5) as f64)
+            .unwrap_or(0.0);
+        let news = self
+            .news_rank
+            .map(|r| 0.5f64.powf(r.saturating_sub(1) as f64 / 2000.0))
+            .unwrap_or(0.0);
+        common.max(jlpt).max(news)
+    }
+}
+
+/// Query-time filter on the priority of items. Empty filters match everything.
+///
+/// Items without any priority data, like items of a `BasicIndex` or custom entries, don't
+/// match non-empty filters unless `keep_unknown` is set.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PriorityFilter {
+    /// Only allow common entries
+    pub common: bool,
+    /// Allowed JLPT levels, eg. `3..=5` for N5 to N3
+    pub jlpt: Option<RangeInclusive<u8>>,
+    /// Highest allowed news rank
+    pub max_news_rank: Option<u32>,
+    /// Let items without any priority data pass
+    pub keep_unknown: bool,
+}
+
+impl PriorityFilter {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[inline]
+    pub fn common_only(mut self) -> Self {
+        self.common = true;
+        self
+    }
+
+    #[inline]
+    pub fn with_jlpt(mut self, levels: RangeInclusive<u8>) -> Self {
+        self.jlpt = Some(levels);
+        self
+    }
+
+    #[inline]
+    pub fn with_max_news_rank(mut self, rank: u32) -> Self {
+        self.max_news_rank = Some(rank);
+        self
+    }
+
+    /// Let items without any priority data pass the filter, eg. for tasks that mix
+    /// dictionary and generic indexes
+    #[inline]
+    pub fn keep_unknown(mut self) -> Self {
+        self.keep_unknown = true;
+        self
+    }
+
+    /// Returns `true` if `priority` fulfills all constraints
+    pub fn matches(&self, priority: &Priority) -> bool {
+        if self.keep_unknown && priority.is_empty() {
+            return true;
+        }
+
+        (!self.common || priority.common)
+            && match &self.jlpt {
+                Some(levels) => priority.jlpt.is_some_and(|l| levels.contains(&l)),
+                None => true,
+            }
+            && match self.max_news_rank {
+                Some(max) => priority.news_rank.is_some_and(|r| r <= max),
+                None => true,
+            }
+    }
+}
@@ -18,10 +18,7 @@ impl StringItem {
 impl ToOutput for StringItem {
     #[inline]
     fn to_output(&self) -> Output {
-        Output {
-            primary: self.word.to_string(),
-            secondary: None,
-        }
+        Output::new(self.word.to_string(), None)
     }
 }
 
@@ -49,8 +46,8 @@ impl IndexItem for StringItem {
     }
 
     #[inline]
-    fn terms(&self) -> Vec<&String> {
-        vec![&self.word]
+    fn terms(&self) -> Vec<&str> {
+        vec![self.word.as_str()]
     }
 
     #[inline]
@@ -0,0 +1,52 @@
+use super::{
+    basic::{builder::BasicIndexBuilder, BasicIndex, Item},
+    japanese::{self, builder::JpIndexBulider, JapaneseIndex},
+    prefix_tree::{PrefixTree, PrefixTreeBuilder},
+    IndexItem,
+};
+use crate::relevance::item::EngineItem;
+
+/// Builds a prefix tree over `words`, using the position of each word as its ID
+pub(crate) fn prefix_tree<'a, I, F>(words: I, freq: F) -> PrefixTree
+where
+    I: IntoIterator<Item = &'a str>,
+    F: Fn(u32) -> f64,
+{
+    let mut builder = PrefixTreeBuilder::new();
+    for (id, word) in words.into_iter().enumerate() {
+        builder.insert(word, id as u32);
+    }
+    builder.build(freq)
+}
+
+/// Builds a basic index over `(word, frequency)` pairs, using the position of each word as
+/// its word ID. `setup` can configure the builder before the words get inserted
+pub(crate) fn basic_index<F>(words: &[(&str, f64)], setup: F) -> BasicIndex
+where
+    F: FnOnce(&mut BasicIndexBuilder),
+{
+    let mut builder = BasicIndexBuilder::new();
+    setup(&mut builder);
+    for (id, (word, freq)) in words.iter().enumerate() {
+        let item = Item::new(word.to_string(), id as u32, *freq);
+        assert!(builder.insert(item, word).is_ok());
+    }
+    builder.build()
+}
+
+/// Builds a Japanese index over `(kana, kanji, frequency)` triples, using the position of
+/// each word as its word ID
+pub(crate) fn japanese_index(words: &[(&str, Option<&str>, f64)]) -> JapaneseIndex {
+    let mut builder = JpIndexBulider::new();
+    for (id, (kana, kanji, freq)) in words.iter().enumerate() {
+        let kanji = kanji.map(|i| i.to_string());
+        let item = japanese::Item::new(id as u32, kana.to_string(), kanji, vec![], *freq);
+        builder.add_item(item);
+    }
+    builder.build()
+}
+
+/// Returns the word IDs of `items` in their order
+pub(crate) fn word_ids(items: &[EngineItem]) -> Vec<u32> {
+    items.iter().map(|i| i.inner().word_id()).collect()
+}
@@ -1,5 +1,6 @@
 pub mod fast_str_diff;
 pub mod index;
+pub mod normalize;
 pub mod relevance;
 pub mod sort_vec;
 pub mod suggest;
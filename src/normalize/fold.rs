@@ -0,0 +1,79 @@
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+
+use super::kana;
+
+/// Removes all diacritics from `inp` and expands letters that don't decompose into a base
+/// letter, eg. "Straße" -> "Strasse", "Café" -> "Cafe"
+pub fn fold_diacritics(inp: &str) -> String {
+    let mut out = String::with_capacity(inp.len());
+
+    for c in inp.nfd().filter(|c| !is_combining_mark(*c)) {
+        match expand_letter(c) {
+            Some(exp) => out.push_str(exp),
+            None => out.push(c),
+        }
+    }
+
+    out
+}
+
+/// Folds a single character for case, diacritic and kana insensitive comparisons without
+/// allocating. Katakana becomes hiragana and dakuten get removed, so "ガ" folds to "か"
+pub fn fold_char(c: char) -> impl Iterator<Item = char> {
+    c.to_lowercase()
+        .nfkd()
+        .filter(|c| !is_combining_mark(*c))
+        .map(kana::katakana_to_hiragana)
+        .flat_map(|c| {
+            let exp = expand_letter(c);
+            exp.unwrap_or_default()
+                .chars()
+                .chain(exp.is_none().then_some(c))
+        })
+}
+
+/// Returns all folded forms of `inp`. German umlauts additionally get transliterated,
+/// eg. "Über" -> ["Uber", "Ueber"]
+pub fn fold_variants(inp: &str) -> Vec<String> {
+    let mut out = vec![fold_diacritics(inp)];
+
+    if inp.contains(|c| matches!(c, 'ä' | 'ö' | 'ü' | 'Ä' | 'Ö' | 'Ü')) {
+        let mut transliterated = String::with_capacity(inp.len() + 2);
+        for c in inp.chars() {
+            match c {
+                'ä' => transliterated.push_str("ae"),
+                'ö' => transliterated.push_str("oe"),
+                'ü' => transliterated.push_str("ue"),
+                'Ä' => transliterated.push_str("Ae"),
+                'Ö' => transliterated.push_str("Oe"),
+                'Ü' => transliterated.push_str("Ue"),
+                _ => transliterated.push(c),
+            }
+        }
+        out.push(fold_diacritics(&transliterated));
+    }
+
+    out
+}
+
+/// Returns the expansion of letters which don't have a decomposition
+#[inline]
+fn expand_letter(c: char) -> Option<&'static str> {
+    Some(match c {
+        'ß' => "ss",
+        'ẞ' => "SS",
+        'æ' => "ae",
+        'Æ' => "AE",
+        'œ' => "oe",
+        'Œ' => "OE",
+        'ø' => "o",
+        'Ø' => "O",
+        'đ' => "d",
+        'Đ' => "D",
+        'ł' => "l",
+        'Ł' => "L",
+        'þ' => "th",
+        'Þ' => "TH",
+        _ => return None,
+    })
+}
@@ -0,0 +1,31 @@
+use unicode_normalization::UnicodeNormalization;
+
+/// Converts all katakana in `inp` to hiragana. Characters without a hiragana counterpart,
+/// like the long vowel mark, are kept
+pub fn to_hiragana(inp: &str) -> String {
+    inp.chars().map(katakana_to_hiragana).collect()
+}
+
+/// Converts a single katakana character to hiragana
+#[inline]
+pub fn katakana_to_hiragana(c: char) -> char {
+    match c {
+        'ァ'..='ヶ' => char::from_u32(c as u32 - 0x60).unwrap_or(c),
+        _ => c,
+    }
+}
+
+/// Returns `true` if `c` is a hiragana or katakana character
+#[inline]
+pub fn is_kana(c: char) -> bool {
+    matches!(c, 'ぁ'..='ゖ' | 'ァ'..='ヺ' | 'ー' | 'ｦ'..='ﾟ')
+}
+
+/// Returns the script independent form of a reading. Half-width katakana gets widened and
+/// all katakana get converted to hiragana, so "コーヒー" and "ｺｰﾋｰ" both become "こーひー"
+pub fn unify(inp: &str) -> String {
+    if inp.is_ascii() {
+        return inp.to_string();
+    }
+    inp.nfkc().map(katakana_to_hiragana).collect()
+}
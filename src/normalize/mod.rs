@@ -0,0 +1,152 @@
+/// Removing diacritics and expanding special letters
+pub mod fold;
+/// Kana conversion helper
+pub mod kana;
+/// Romaji to kana conversion
+pub mod romaji;
+
+use serde::{Deserialize, Serialize};
+use unicode_normalization::UnicodeNormalization;
+
+/// Normalizes indexed terms and queries
+pub trait Normalizer: Send + Sync {
+    fn normalize(&self, inp: &str) -> String;
+}
+
+impl<F> Normalizer for F
+where
+    F: Fn(&str) -> String + Send + Sync,
+{
+    #[inline]
+    fn normalize(&self, inp: &str) -> String {
+        (self)(inp)
+    }
+}
+
+/// A single normalization step
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Step {
+    /// Removes all given characters
+    StripPunctuation(Vec<char>),
+    Lowercase,
+    /// Unicode compatibility composition. Turns full-width latin characters and half-width
+    /// katakana into their regular forms
+    Nfkc,
+    /// Removes diacritics (é -> e) and expands special letters (ß -> ss)
+    FoldDiacritics,
+    /// Converts katakana into hiragana
+    UnifyKana,
+}
+
+impl Step {
+    /// Strips the punctuation that is common in indexed terms
+    pub fn punctuation() -> Self {
+        Self::StripPunctuation(vec![
+            '(', ')', '.', ',', '/', '[', ']', '?', '!', '{', '}', '、', '。', '・',
+        ])
+    }
+
+    /// Returns the name of the step
+    pub fn name(&self) -> &'static str {
+        match self {
+            Step::StripPunctuation(_) => "strip_punctuation",
+            Step::Lowercase => "lowercase",
+            Step::Nfkc => "nfkc",
+            Step::FoldDiacritics => "fold_diacritics",
+            Step::UnifyKana => "unify_kana",
+        }
+    }
+}
+
+impl Normalizer for Step {
+    fn normalize(&self, inp: &str) -> String {
+        match self {
+            Step::StripPunctuation(chars) => inp.chars().filter(|c| !chars.contains(c)).collect(),
+            Step::Lowercase => inp.to_lowercase(),
+            Step::Nfkc => inp.nfkc().collect(),
+            Step::FoldDiacritics => fold::fold_diacritics(inp),
+            Step::UnifyKana => kana::to_hiragana(inp),
+        }
+    }
+}
+
+/// Ordered set of normalization steps. Indexes store the pipeline they were built with and
+/// apply it to all queries, so terms and queries always get normalized the same way.
+/// An empty pipeline leaves the input untouched.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NormalizerPipeline {
+    steps: Vec<Step>,
+}
+
+impl NormalizerPipeline {
+    /// Create a new empty pipeline
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends a step to the pipeline
+    #[inline]
+    pub fn with(mut self, step: Step) -> Self {
+        self.steps.push(step);
+        self
+    }
+
+    /// Pipeline for generic terms
+    pub fn basic() -> Self {
+        let punctuation = vec![
+            '(', ')', '.', ',', '/', '[', ']', '?', '{', '}', '、', '。', '・',
+        ];
+        Self::new()
+            .with(Step::StripPunctuation(punctuation))
+            .with(Step::Lowercase)
+    }
+
+    /// Pipeline for generic terms which additionally turns full-width characters and
+    /// ligatures into their regular forms
+    pub fn basic_unicode() -> Self {
+        let mut pipeline = Self::new().with(Step::Nfkc);
+        pipeline.steps.extend(Self::basic().steps);
+        pipeline
+    }
+
+    /// Pipeline for Japanese terms
+    pub fn japanese() -> Self {
+        Self::new().with(Step::punctuation()).with(Step::Lowercase)
+    }
+
+    /// Returns the steps of the pipeline
+    #[inline]
+    pub fn steps(&self) -> &[Step] {
+        &self.steps
+    }
+
+    /// Returns `true` if the pipeline doesn't modify its input
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    /// Returns a short description of the pipeline
+    pub fn config(&self) -> String {
+        self.steps
+            .iter()
+            .map(|i| match i {
+                Step::StripPunctuation(chars) => {
+                    format!("{}({})", i.name(), chars.iter().collect::<String>())
+                }
+                _ => i.name().to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+}
+
+impl Normalizer for NormalizerPipeline {
+    #[inline]
+    fn normalize(&self, inp: &str) -> String {
+        self.steps
+            .iter()
+            .fold(inp.to_string(), |out, step| step.normalize(&out))
+    }
+}
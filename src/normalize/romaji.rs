@@ -0,0 +1,309 @@
+use super::kana;
+
+/// Romaji syllables and their hiragana
+#[rustfmt::skip]
+static SYLLABLES: &[(&str, &str)] = &[
+    ("a", "あ"), ("i", "い"), ("u", "う"), ("e", "え"), ("o", "お"),
+    ("ka", "か"), ("ki", "き"), ("ku", "く"), ("ke", "け"), ("ko", "こ"),
+    ("ga", "が"), ("gi", "ぎ"), ("gu", "ぐ"), ("ge", "げ"), ("go", "ご"),
+    ("sa", "さ"), ("si", "し"), ("shi", "し"), ("su", "す"), ("se", "せ"), ("so", "そ"),
+    ("za", "ざ"), ("zi", "じ"), ("ji", "じ"), ("zu", "ず"), ("ze", "ぜ"), ("zo", "ぞ"),
+    ("ta", "た"), ("ti", "ち"), ("chi", "ち"), ("tu", "つ"), ("tsu", "つ"), ("te", "て"), ("to", "と"),
+    ("da", "だ"), ("di", "ぢ"), ("du", "づ"), ("de", "で"), ("do", "ど"),
+    ("na", "な"), ("ni", "に"), ("nu", "ぬ"), ("ne", "ね"), ("no", "の"),
+    ("ha", "は"), ("hi", "ひ"), ("hu", "ふ"), ("fu", "ふ"), ("he", "へ"), ("ho", "ほ"),
+    ("ba", "ば"), ("bi", "び"), ("bu", "ぶ"), ("be", "べ"), ("bo", "ぼ"),
+    ("pa", "ぱ"), ("pi", "ぴ"), ("pu", "ぷ"), ("pe", "ぺ"), ("po", "ぽ"),
+    ("ma", "ま"), ("mi", "み"), ("mu", "む"), ("me", "め"), ("mo", "も"),
+    ("ya", "や"), ("yu", "ゆ"), ("yo", "よ"),
+    ("ra", "ら"), ("ri", "り"), ("ru", "る"), ("re", "れ"), ("ro", "ろ"),
+    ("wa", "わ"), ("wo", "を"),
+    ("kya", "きゃ"), ("kyu", "きゅ"), ("kyo", "きょ"),
+    ("gya", "ぎゃ"), ("gyu", "ぎゅ"), ("gyo", "ぎょ"),
+    ("sya", "しゃ"), ("syu", "しゅ"), ("syo", "しょ"),
+    ("sha", "しゃ"), ("shu", "しゅ"), ("she", "しぇ"), ("sho", "しょ"),
+    ("ja", "じゃ"), ("ju", "じゅ"), ("je", "じぇ"), ("jo", "じょ"),
+    ("zya", "じゃ"), ("zyu", "じゅ"), ("zyo", "じょ"),
+    ("jya", "じゃ"), ("jyu", "じゅ"), ("jyo", "じょ"),
+    ("tya", "ちゃ"), ("tyu", "ちゅ"), ("tyo", "ちょ"),
+    ("cha", "ちゃ"), ("chu", "ちゅ"), ("che", "ちぇ"), ("cho", "ちょ"),
+    ("nya", "にゃ"), ("nyu", "にゅ"), ("nyo", "にょ"),
+    ("hya", "ひゃ"), ("hyu", "ひゅ"), ("hyo", "ひょ"),
+    ("bya", "びゃ"), ("byu", "びゅ"), ("byo", "びょ"),
+    ("pya", "ぴゃ"), ("pyu", "ぴゅ"), ("pyo", "ぴょ"),
+    ("mya", "みゃ"), ("myu", "みゅ"), ("myo", "みょ"),
+    ("rya", "りゃ"), ("ryu", "りゅ"), ("ryo", "りょ"),
+    ("fa", "ふぁ"), ("fi", "ふぃ"), ("fe", "ふぇ"), ("fo", "ふぉ"),
+    ("xa", "ぁ"), ("xi", "ぃ"), ("xu", "ぅ"), ("xe", "ぇ"), ("xo", "ぉ"),
+    ("xya", "ゃ"), ("xyu", "ゅ"), ("xyo", "ょ"), ("xtu", "っ"), ("xtsu", "っ"),
+];
+
+/// Length of the longest romaji syllable
+const MAX_SYLLABLE_LEN: usize = 4;
+
+/// A kana prefix a romaji input can stand for
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RomajiPrefix {
+    pub kana: String,
+    /// `true` if the input ended with a partial syllable which got expanded to one of the
+    /// kana it can start
+    pub partial: bool,
+}
+
+/// Converts romaji input into all kana prefixes it can stand for. Complete input results
+/// in a single prefix ("kyou" -> きょう), a dangling consonant gets expanded to every kana
+/// it can start ("tab" -> たば, たび, たぶ, たべ, たぼ; "shin" -> しん, しな, しに, ..).
+/// Returns an empty vec if `inp` is not romaji.
+pub fn romaji_prefixes(inp: &str) -> Vec<RomajiPrefix> {
+    if !is_romaji(inp) {
+        return vec![];
+    }
+    let inp = inp.to_lowercase();
+
+    let mut kana = String::with_capacity(inp.len() * 3);
+    let mut pos = 0;
+
+    while pos < inp.len() {
+        let rest = &inp[pos..];
+        let c = rest.chars().next().unwrap();
+
+        if !c.is_ascii_alphabetic() {
+            kana.push(if c == '-' { 'ー' } else { c });
+            pos += c.len_utf8();
+            continue;
+        }
+
+        if let Some((romaji, syllable)) = longest_syllable(rest) {
+            kana.push_str(syllable);
+            pos += romaji.len();
+            continue;
+        }
+
+        // Incomplete syllables at the end of the input
+        if starts_syllable(rest) {
+            return partial_prefixes(&kana, rest);
+        }
+
+        let next = rest[1..].chars().next();
+        match (c, next) {
+            ('n', Some('n')) if rest.len() == 2 => {
+                kana.push('ん');
+                pos += 2;
+            }
+            ('n', Some('\'')) => {
+                kana.push('ん');
+                pos += 2;
+            }
+            ('n', Some(n)) if n.is_ascii_alphabetic() => {
+                kana.push('ん');
+                pos += 1;
+            }
+            // Doubled consonants like in "kitte"
+            (c, Some(n)) if c == n && is_consonant(c) => {
+                kana.push('っ');
+                pos += 1;
+            }
+            _ => return vec![],
+        }
+    }
+
+    vec![RomajiPrefix {
+        kana,
+        partial: false,
+    }]
+}
+
+/// Returns the longest syllable `inp` starts with
+fn longest_syllable(inp: &str) -> Option<(&'static str, &'static str)> {
+    (1..=MAX_SYLLABLE_LEN.min(inp.len()))
+        .rev()
+        .filter(|len| inp.is_char_boundary(*len))
+        .find_map(|len| {
+            let part = &inp[..len];
+            SYLLABLES
+                .iter()
+                .find(|(romaji, _)| *romaji == part)
+                .copied()
+        })
+}
+
+/// Returns `true` if `inp` is the beginning of a syllable
+#[inline]
+fn starts_syllable(inp: &str) -> bool {
+    inp.len() < MAX_SYLLABLE_LEN && SYLLABLES.iter().any(|(romaji, _)| romaji.starts_with(inp))
+}
+
+/// Expands the incomplete syllable `tail` to all kana it can start
+fn partial_prefixes(kana: &str, tail: &str) -> Vec<RomajiPrefix> {
+    let mut first_kana: Vec<char> = vec![];
+    if tail == "n" {
+        first_kana.push('ん');
+    } else if tail.len() == 1 && tail.chars().all(is_consonant) {
+        first_kana.push('っ');
+    }
+
+    for (romaji, syllable) in SYLLABLES {
+        if !romaji.starts_with(tail) {
+            continue;
+        }
+        let c = syllable.chars().next().unwrap();
+        if !first_kana.contains(&c) {
+            first_kana.push(c);
+        }
+    }
+
+    first_kana
+        .into_iter()
+        .map(|c| {
+            let mut kana = kana.to_string();
+            kana.push(c);
+            RomajiPrefix {
+                kana,
+                partial: true,
+            }
+        })
+        .collect()
+}
+
+#[inline]
+fn is_consonant(c: char) -> bool {
+    c.is_ascii_alphabetic() && !matches!(c, 'a' | 'i' | 'u' | 'e' | 'o')
+}
+
+/// Returns `true` if `inp` contains characters that could be romaji
+#[inline]
+pub fn is_romaji(inp: &str) -> bool {
+    inp.chars().any(|c| c.is_ascii_alphabetic())
+}
+
+/// Spellings of `SYLLABLES` which are not Hepburn
+#[rustfmt::skip]
+static NON_HEPBURN: &[&str] = &[
+    "si", "zi", "ti", "tu", "hu", "di", "du", "sya", "syu", "syo", "zya", "zyu", "zyo",
+    "jya", "jyu", "jyo", "tya", "tyu", "tyo", "xtu", "xtsu",
+];
+
+/// Converts kana into Hepburn romaji ("たべる" -> taberu, "きって" -> kitte,
+/// "ラーメン" -> raamen). Characters that aren't kana are kept as they are
+pub fn to_hepburn(inp: &str) -> String {
+    let chars: Vec<char> = kana::to_hiragana(inp).chars().collect();
+    let mut out = String::with_capacity(chars.len() * 2);
+    let mut sokuon = false;
+    let mut pos = 0;
+
+    while pos < chars.len() {
+        let c = chars[pos];
+
+        let found = [2, 1].into_iter().find_map(|len| {
+            let part: String = chars.get(pos..pos + len)?.iter().collect();
+            Some((len, hepburn_syllable(&part)?))
+        });
+
+        let (len, romaji) = match found {
+            Some(found) => found,
+            None => {
+                match c {
+                    'っ' => sokuon = true,
+                    'ん' => {
+                        out.push('n');
+                        let next = chars
+                            .get(pos + 1)
+                            .and_then(|i| hepburn_syllable(&i.to_string()));
+                        if next.is_some_and(|i| i.starts_with(['a', 'i', 'u', 'e', 'o', 'y'])) {
+                            out.push('\'');
+                        }
+                    }
+                    'ー' => {
+                        if let Some(vowel) = out.chars().last().filter(|i| "aiueo".contains(*i)) {
+                            out.push(vowel);
+                        }
+                    }
+                    _ => out.push(c),
+                }
+                pos += 1;
+                continue;
+            }
+        };
+
+        if sokuon {
+            match romaji.chars().next() {
+                Some('c') => out.push('t'),
+                Some(c) if is_consonant(c) => out.push(c),
+                _ => out.push_str("xtsu"),
+            }
+            sokuon = false;
+        }
+
+        out.push_str(romaji);
+        pos += len;
+    }
+
+    out
+}
+
+/// Returns the Hepburn spelling of a single syllable
+fn hepburn_syllable(kana: &str) -> Option<&'static str> {
+    match kana {
+        "ぢ" => return Some("ji"),
+        "づ" => return Some("zu"),
+        _ => (),
+    }
+
+    SYLLABLES
+        .iter()
+        .find(|(romaji, syllable)| *syllable == kana && !NON_HEPBURN.contains(romaji))
+        .map(|i| i.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn prefixes(inp: &str) -> Vec<String> {
+        romaji_prefixes(inp).into_iter().map(|i| i.kana).collect()
+    }
+
+    #[test]
+    fn test_complete_input() {
+        let out = romaji_prefixes("kyou");
+        assert_eq!(
+            out,
+            vec![RomajiPrefix {
+                kana: "きょう".to_string(),
+                partial: false,
+            }]
+        );
+        assert_eq!(prefixes("kitte"), vec!["きって"]);
+        assert_eq!(prefixes("konnichiha"), vec!["こんにちは"]);
+        assert_eq!(prefixes("Shi-"), vec!["しー"]);
+    }
+
+    #[test]
+    fn test_partial_tail() {
+        assert_eq!(
+            prefixes("tab"),
+            vec!["たっ", "たば", "たび", "たぶ", "たべ", "たぼ"]
+        );
+        assert_eq!(
+            prefixes("shin"),
+            vec!["しん", "しな", "しに", "しぬ", "しね", "しの"]
+        );
+        assert!(romaji_prefixes("tab").iter().all(|i| i.partial));
+    }
+
+    #[test]
+    fn test_not_romaji() {
+        assert!(romaji_prefixes("ねこ").is_empty());
+        assert!(romaji_prefixes("").is_empty());
+    }
+
+    #[test]
+    fn test_to_hepburn() {
+        assert_eq!(to_hepburn("たべる"), "taberu");
+        assert_eq!(to_hepburn("きって"), "kitte");
+        assert_eq!(to_hepburn("ラーメン"), "raamen");
+        assert_eq!(to_hepburn("きょう"), "kyou");
+        assert_eq!(to_hepburn("しんいち"), "shin'ichi");
+        assert_eq!(to_hepburn("まっちゃ"), "matcha");
+    }
+}
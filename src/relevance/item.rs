@@ -1,12 +1,18 @@
-use std::{fmt::Debug, hash::Hash};
+use std::{cmp::Ordering, fmt::Debug, hash::Hash};
 
-use crate::index::{IndexItem, Output};
+use super::ScoreInput;
+use crate::{
+    index::{IndexItem, Output},
+    suggest::result::Source,
+};
 use order_struct::OrderVal;
 
 /// Wrapper around IndexItem with ordering
-#[derive(PartialOrd, Ord, Clone, Copy)]
+#[derive(Clone, Copy)]
 pub struct EngineItem<'a> {
     item: OrderVal<&'a dyn IndexItem, u16>,
+    source: Source,
+    score: Option<ScoreInput>,
 }
 
 impl<'a> EngineItem<'a> {
@@ -14,9 +20,29 @@ impl<'a> EngineItem<'a> {
     pub fn new(val: &'a dyn IndexItem, relevance: u16) -> Self {
         Self {
             item: OrderVal::new(val, relevance),
+            source: Source::default(),
+            score: None,
         }
     }
 
+    /// Sets the stage that produced the item
+    #[inline]
+    pub fn with_source(mut self, source: Source) -> Self {
+        self.source = source;
+        self
+    }
+
+    /// Returns the stage that produced the item
+    #[inline]
+    pub fn source(&self) -> Source {
+        self.source
+    }
+
+    #[inline]
+    pub fn set_source(&mut self, source: Source) {
+        self.source = source;
+    }
+
     /// Convert to output
     #[inline]
     pub fn to_output(self) -> Output {
@@ -34,6 +60,17 @@ impl<'a> EngineItem<'a> {
         self.item.set_ord(new_val)
     }
 
+    /// Returns the inputs of the last relevance calculation of the item
+    #[inline]
+    pub fn score_input(&self) -> Option<ScoreInput> {
+        self.score
+    }
+
+    #[inline]
+    pub fn set_score_input(&mut self, score: ScoreInput) {
+        self.score = Some(score);
+    }
+
     /// Get the order value of the item
     #[inline]
     pub fn get_relevance(&self) -> u16 {
@@ -67,3 +104,17 @@ impl<'a> PartialEq for EngineItem<'a> {
 }
 
 impl<'a> Eq for EngineItem<'a> {}
+
+impl<'a> PartialOrd for EngineItem<'a> {
+    #[inline]
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<'a> Ord for EngineItem<'a> {
+    #[inline]
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.item.cmp(&other.item)
+    }
+}
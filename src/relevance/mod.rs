@@ -1,13 +1,19 @@
 use self::item::EngineItem;
+use serde::{Deserialize, Serialize};
 
 pub mod item;
+/// Ranking signal learned from selected suggestions
+pub mod selection;
 
 /// Collection of different types of weights for a relevance calculation
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct RelevanceWeights {
     pub str_weight: f64,
     pub freq_weight: f64,
     pub total_weight: f64,
+    /// Weight of the items priority metadata (see `Priority::score`)
+    pub priority_weight: f64,
 }
 
 impl RelevanceWeights {
@@ -17,10 +23,21 @@ impl RelevanceWeights {
             str_weight,
             freq_weight,
             total_weight,
+            priority_weight: DEFAULT_PRIORITY_WEIGHT,
         }
     }
+
+    #[inline]
+    pub fn with_priority_weight(mut self, priority_weight: f64) -> Self {
+        self.priority_weight = priority_weight;
+        self
+    }
 }
 
+/// Default weight of priority metadata. Lower than the frequency weight so corpus
+/// frequencies stay the main signal where available
+const DEFAULT_PRIORITY_WEIGHT: f64 = 0.5;
+
 impl Default for RelevanceWeights {
     #[inline]
     fn default() -> Self {
@@ -28,6 +45,7 @@ impl Default for RelevanceWeights {
             str_weight: 1.0,
             freq_weight: 1.0,
             total_weight: 1.0,
+            priority_weight: DEFAULT_PRIORITY_WEIGHT,
         }
     }
 }
@@ -54,6 +72,24 @@ impl RelevanceCalc {
     /// Executes relevance calculation for a given Item
     #[inline]
     pub fn calc(&self, item: &EngineItem, str_rel: u16) -> u16 {
+        self.explain(item, str_rel).relevance
+    }
+
+    /// Calculates the relevance of `item` and sets it. The inputs of the calculation are
+    /// kept within the item so its score can be explained later on
+    #[inline]
+    pub fn apply(&self, item: &mut EngineItem, str_rel: u16) {
+        let relevance = self.calc(item, str_rel);
+        item.set_relevance(relevance);
+        item.set_score_input(ScoreInput {
+            str_rel,
+            weights: self.weights,
+        });
+    }
+
+    /// Executes relevance calculation for a given Item and returns all of its components
+    #[inline]
+    pub fn explain(&self, item: &EngineItem, str_rel: u16) -> ScoreExplanation {
         let srel = ((str_rel as f64) * self.weights.str_weight).min(1000.0);
         let mut frel =
             ((item.inner().frequency() * 1000000.0) * self.weights.freq_weight).min(1000.0);
@@ -61,8 +97,48 @@ impl RelevanceCalc {
         if frel > 0.0 && frel < 1.0 {
             frel = 1.0;
         }
-        let calc = (srel + frel + 1.0) * self.weights.total_weight;
+        let prel =
+            (item.inner().priority().score() * 1000.0 * self.weights.priority_weight).min(1000.0);
+        let calc = (srel + frel + prel + 1.0) * self.weights.total_weight;
         // TODO: maybe use f32 instead of u16..
-        (calc * 10.0) as u16
+        ScoreExplanation {
+            str_rel,
+            str_score: srel,
+            freq_score: frel,
+            priority_score: prel,
+            weights: self.weights,
+            extension_weight: 1.0,
+            relevance: (calc * 10.0) as u16,
+            rel_mod: 0,
+        }
     }
 }
+
+/// Inputs of a relevance calculation
+#[derive(Clone, Copy, Debug)]
+pub struct ScoreInput {
+    pub str_rel: u16,
+    pub weights: RelevanceWeights,
+}
+
+/// Components of an items relevance
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ScoreExplanation {
+    /// Raw string relevance of the item to the query
+    pub str_rel: u16,
+    /// String component, weighted with `weights.str_weight`
+    pub str_score: f64,
+    /// Frequency component, weighted with `weights.freq_weight`
+    pub freq_score: f64,
+    /// Priority component, weighted with `weights.priority_weight`
+    #[serde(default)]
+    pub priority_score: f64,
+    /// Weights of the calculation. `total_weight` includes the extension multiplier
+    pub weights: RelevanceWeights,
+    /// Multiplier of the extension that found the item
+    pub extension_weight: f64,
+    /// Relevance calculated from the components above
+    pub relevance: u16,
+    /// Change of the relevance done by the tasks `rel_mod` and personalization
+    pub rel_mod: i32,
+}
@@ -0,0 +1,226 @@
+use std::{
+    collections::HashMap,
+    sync::{PoisonError, RwLock},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use super::item::EngineItem;
+
+/// Default half-life of a selection in seconds (30 days)
+pub const DEFAULT_HALF_LIFE: u64 = 30 * 24 * 60 * 60;
+
+/// Default highest relevance boost of a selected word
+pub const DEFAULT_MAX_BOOST: u16 = 5000;
+
+/// Frequency at which the frequency score of `RelevanceCalc` saturates
+const SATURATED_FREQUENCY: f64 = 0.001;
+
+/// Records which suggestions users picked and turns them into a ranking signal. Selections
+/// lose half of their weight every `half_life` seconds, so the signal follows recent user
+/// behavior. Can be shared across threads.
+///
+/// The signal can be used at query time with `rel_mod`/`rel_mod_for` or be blended into
+/// the frequencies of a rebuilt index with `blend_frequency`.
+pub struct SelectionLog {
+    data: RwLock<SelectionData>,
+    half_life: u64,
+    /// Highest relevance boost a selected item can get
+    pub max_boost: u16,
+    /// Weight of the selection signal when blending it into a frequency
+    pub blend_weight: f64,
+}
+
+/// Aggregated selections. Can be serialized to persist a `SelectionLog`
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SelectionData {
+    /// Selections of each word
+    words: HashMap<u32, Decayed>,
+    /// Selections of each word for a given prefix
+    prefixes: HashMap<String, HashMap<u32, Decayed>>,
+}
+
+/// Exponentially decaying counter
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Decayed {
+    value: f64,
+    /// Unix timestamp of the last update of `value`
+    updated: u64,
+}
+
+impl Decayed {
+    /// Returns the value of the counter at the unix timestamp `time`
+    #[inline]
+    pub fn value_at(&self, time: u64, half_life: u64) -> f64 {
+        let elapsed = time.saturating_sub(self.updated) as f64;
+        self.value * 0.5f64.powf(elapsed / half_life.max(1) as f64)
+    }
+
+    /// Increments the counter at the unix timestamp `time`
+    #[inline]
+    pub fn add(&mut self, time: u64, half_life: u64) {
+        self.value = self.value_at(time, half_life) + 1.0;
+        self.updated = self.updated.max(time);
+    }
+
+    /// Unix timestamp of the last increment
+    #[inline]
+    pub fn updated(&self) -> u64 {
+        self.updated
+    }
+}
+
+/// Turns a decayed score into a relevance boost. Saturates towards `max_boost` for
+/// frequently selected words
+#[inline]
+pub fn relevance_boost(score: f64, max_boost: u16) -> u16 {
+    let share = score / (score + 1.0);
+    (share * max_boost as f64) as u16
+}
+
+impl SelectionLog {
+    /// Create a new empty SelectionLog with a half-life of 30 days
+    pub fn new() -> Self {
+        Self::from_data(SelectionData::default())
+    }
+
+    /// Create a SelectionLog out of previously recorded selections
+    pub fn from_data(data: SelectionData) -> Self {
+        Self {
+            data: RwLock::new(data),
+            half_life: DEFAULT_HALF_LIFE,
+            max_boost: DEFAULT_MAX_BOOST,
+            blend_weight: 0.3,
+        }
+    }
+
+    /// Sets the time in seconds after which a selection has lost half of its weight
+    #[inline]
+    pub fn with_half_life(mut self, half_life: u64) -> Self {
+        self.half_life = half_life;
+        self
+    }
+
+    /// Returns a copy of all recorded selections, eg. to persist them
+    pub fn data(&self) -> SelectionData {
+        self.data
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Records that the word with `word_id` was picked for the (normalized) query `prefix`
+    #[inline]
+    pub fn record(&self, prefix: &str, word_id: u32) {
+        self.record_at(prefix, word_id, now());
+    }
+
+    /// Records a selection that happened at the given unix timestamp
+    pub fn record_at(&self, prefix: &str, word_id: u32, time: u64) {
+        let mut data = self.data.write().unwrap_or_else(PoisonError::into_inner);
+        data.words
+            .entry(word_id)
+            .or_default()
+            .add(time, self.half_life);
+        data.prefixes
+            .entry(prefix.to_string())
+            .or_default()
+            .entry(word_id)
+            .or_default()
+            .add(time, self.half_life);
+    }
+
+    /// Returns the IDs of all words that were selected at least once
+    pub fn word_ids(&self) -> Vec<u32> {
+        let data = self.data.read().unwrap_or_else(PoisonError::into_inner);
+        data.words.keys().copied().collect()
+    }
+
+    /// Returns the decayed amount of selections of a word
+    pub fn score(&self, word_id: u32) -> f64 {
+        let data = self.data.read().unwrap_or_else(PoisonError::into_inner);
+        data.words
+            .get(&word_id)
+            .map(|i| i.value_at(now(), self.half_life))
+            .unwrap_or(0.0)
+    }
+
+    /// Returns the decayed amount of selections of a word for `prefix`
+    pub fn prefix_score(&self, prefix: &str, word_id: u32) -> f64 {
+        let data = self.data.read().unwrap_or_else(PoisonError::into_inner);
+        data.prefixes
+            .get(prefix)
+            .and_then(|i| i.get(&word_id))
+            .map(|i| i.value_at(now(), self.half_life))
+            .unwrap_or(0.0)
+    }
+
+    /// Returns the relevance boost of a word. Selections for the same prefix count double
+    pub fn boost(&self, prefix: Option<&str>, word_id: u32) -> u16 {
+        let mut score = self.score(word_id);
+        if let Some(prefix) = prefix {
+            score += self.prefix_score(prefix, word_id);
+        }
+        relevance_boost(score, self.max_boost)
+    }
+
+    /// Returns a relevance modifier which boosts frequently selected words. Can be passed to
+    /// `SuggestionTask::set_rel_mod` or `SuggestionPipeline::set_rel_mod`
+    pub fn rel_mod(&self) -> impl Fn(&EngineItem, u16) -> u16 + Send + Sync + '_ {
+        move |item, rel| rel.saturating_add(self.boost(None, item.inner().word_id()))
+    }
+
+    /// Same as `rel_mod` but also boosts words that were selected for `prefix`
+    pub fn rel_mod_for<'a>(
+        &'a self,
+        prefix: &'a str,
+    ) -> impl Fn(&EngineItem, u16) -> u16 + Send + Sync + 'a {
+        move |item, rel| rel.saturating_add(self.boost(Some(prefix), item.inner().word_id()))
+    }
+
+    /// Returns the frequency a word gains from its selections. Frequently selected words gain
+    /// up to `blend_weight` of the highest frequency score
+    pub fn frequency_boost(&self, word_id: u32) -> f64 {
+        let score = self.score(word_id);
+        let share = score / (score + 1.0);
+        share * SATURATED_FREQUENCY * self.blend_weight
+    }
+
+    /// Blends the selections of a word into its corpus frequency. Use this to bake the
+    /// selections into the frequencies of a rebuilt index. `frequency` has to be the corpus
+    /// frequency without any earlier blends
+    #[inline]
+    pub fn blend_frequency(&self, word_id: u32, frequency: f64) -> f64 {
+        (frequency + self.frequency_boost(word_id)).min(1.0)
+    }
+
+    /// Drops all selections whose weight decayed below `min_score`
+    pub fn prune(&self, min_score: f64) {
+        let time = now();
+        let half_life = self.half_life;
+        let mut data = self.data.write().unwrap_or_else(PoisonError::into_inner);
+        data.words
+            .retain(|_, i| i.value_at(time, half_life) >= min_score);
+        data.prefixes.retain(|_, words| {
+            words.retain(|_, i| i.value_at(time, half_life) >= min_score);
+            !words.is_empty()
+        });
+    }
+}
+
+impl Default for SelectionLog {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Returns the current unix timestamp
+#[inline]
+pub(crate) fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|i| i.as_secs())
+        .unwrap_or(0)
+}
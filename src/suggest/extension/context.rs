@@ -0,0 +1,74 @@
+use super::{Extension, ExtensionOptions};
+use crate::{
+    index::{
+        language_model::{score_relevance, split_query, LanguageModel},
+        SuggestionIndex,
+    },
+    relevance::{item::EngineItem, RelevanceCalc},
+    suggest::{query::SuggestionQuery, result::Source},
+};
+
+/// Completes the last token of a multi-word query based on the tokens before it, or
+/// predicts the next word if the query ends with whitespace. Only runs on queries with at
+/// least one preceding token
+pub struct ContextExtension<'a> {
+    pub options: ExtensionOptions,
+    model: &'a LanguageModel,
+}
+
+impl<'a> ContextExtension<'a> {
+    /// Create a new Context-Extension using the given language model
+    pub fn new(model: &'a LanguageModel) -> Self {
+        let mut options = ExtensionOptions::default();
+        // Context predictions are useful regardless of what the query found itself
+        options.threshold = usize::MAX;
+        Self { options, model }
+    }
+
+    /// Normalizes the query of another index the same way the model normalized its corpus
+    #[inline]
+    fn model_query(&self, query: &SuggestionQuery) -> String {
+        match self.model.normalizer() {
+            Some(normalizer) => normalizer.normalize(&query.query_str),
+            None => query.query_str.clone(),
+        }
+    }
+}
+
+impl<'a> Extension<'a> for ContextExtension<'a> {
+    fn run(&self, query: &SuggestionQuery, rel_weight: f64) -> Vec<EngineItem<'a>> {
+        let rel_weight = rel_weight * self.options.weights.total_weight;
+        let rel_calc = RelevanceCalc::new(self.options.weights).with_total_weight(rel_weight);
+
+        let query_str = self.model_query(query);
+        let (context, prefix) = split_query(&query_str);
+
+        self.model
+            .continuations(&context, prefix, self.options.limit)
+            .into_iter()
+            .filter_map(|(id, score)| {
+                let mut item = self.model.get_word(id)?;
+                rel_calc.apply(&mut item, score_relevance(score));
+                Some(item)
+            })
+            .collect()
+    }
+
+    #[inline]
+    fn should_run(&self, already_found: usize, query: &SuggestionQuery) -> bool {
+        self.options.enabled
+            && already_found < self.options.threshold
+            && query.len() >= self.options.min_query_len
+            && !split_query(&query.query_str).0.is_empty()
+    }
+
+    #[inline]
+    fn get_options(&self) -> &ExtensionOptions {
+        &self.options
+    }
+
+    #[inline]
+    fn source(&self) -> Source {
+        Source::Context
+    }
+}
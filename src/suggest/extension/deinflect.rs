@@ -0,0 +1,86 @@
+use std::collections::HashSet;
+
+use priority_container::PrioContainerMax;
+
+use super::{Extension, ExtensionOptions};
+use crate::{
+    index::{
+        japanese::deinflect::{self, Inflections},
+        SuggestionIndex,
+    },
+    normalize::kana,
+    relevance::{item::EngineItem, RelevanceCalc},
+    suggest::{query::SuggestionQuery, result::Source},
+};
+
+/// Finds the dictionary forms of inflected Japanese queries ("食べなかった" -> 食べる). Items
+/// report the removed inflections in their source
+#[derive(Clone, Copy)]
+pub struct DeinflectExtension<'a> {
+    pub options: ExtensionOptions,
+    index: &'a (dyn SuggestionIndex + Sync),
+    /// Also suggest words starting with a deinflected form
+    pub predict: bool,
+}
+
+impl<'a> DeinflectExtension<'a> {
+    /// Create a new Deinflection Extension
+    pub fn new(index: &'a (dyn SuggestionIndex + Sync)) -> Self {
+        let mut options = ExtensionOptions::default();
+        options.min_query_len = 2;
+        Self {
+            options,
+            index,
+            predict: false,
+        }
+    }
+}
+
+impl<'a> Extension<'a> for DeinflectExtension<'a> {
+    fn run(&self, query: &SuggestionQuery, rel_weight: f64) -> Vec<EngineItem<'a>> {
+        let rel_weight = rel_weight * self.options.weights.total_weight;
+        let rel_calc = RelevanceCalc::new(self.options.weights).with_total_weight(rel_weight);
+
+        let mut out = PrioContainerMax::new(self.options.limit);
+        let mut found = HashSet::with_capacity(self.options.limit);
+
+        let query_str = kana::unify(&query.query_str);
+
+        for candidate in deinflect::deinflect(&query_str) {
+            let mut items = self.index.exact(&candidate.word);
+            if self.predict {
+                items.extend(self.index.predictions(&candidate.word, self.options.limit));
+            }
+
+            for item in items {
+                if !found.insert(item) {
+                    continue;
+                }
+
+                let str_rel = item.inner().str_relevance(&candidate.word);
+                let mut item = item.with_source(Source::Deinflection(candidate.inflections));
+                rel_calc.apply(&mut item, str_rel);
+                out.insert(item);
+            }
+        }
+
+        out.into_iter().map(|i| i.0).collect()
+    }
+
+    #[inline]
+    fn should_run(&self, already_found: usize, query: &SuggestionQuery) -> bool {
+        self.options.enabled
+            && already_found < self.options.threshold
+            && query.len() >= self.options.min_query_len
+    }
+
+    #[inline]
+    fn get_options(&self) -> &ExtensionOptions {
+        &self.options
+    }
+
+    #[inline]
+    fn source(&self) -> Source {
+        Source::Deinflection(Inflections::default())
+    }
+}
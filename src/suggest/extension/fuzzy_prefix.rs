@@ -0,0 +1,80 @@
+use priority_container::PrioContainerMax;
+
+use super::{Extension, ExtensionOptions};
+use crate::{
+    index::{prefix_tree::EDIT_COST, SuggestionIndex},
+    normalize::kana,
+    relevance::{item::EngineItem, RelevanceCalc},
+    suggest::{query::SuggestionQuery, result::Source},
+};
+
+/// Completes misspelled prefixes by walking the index with a Levenshtein automaton
+#[derive(Clone, Copy)]
+pub struct FuzzyPrefixExtension<'a> {
+    pub options: ExtensionOptions,
+    index: &'a (dyn SuggestionIndex + Sync),
+    /// Max edits allowed between the query and the prefix of a suggestion
+    pub max_dist: u32,
+    /// Min length of queries written in kana. Replaces `options.min_query_len` for them, as
+    /// a single kana carries about as much as two latin letters
+    pub min_kana_len: usize,
+}
+
+impl<'a> FuzzyPrefixExtension<'a> {
+    /// Create a new Fuzzy-Prefix Extension
+    pub fn new(index: &'a (dyn SuggestionIndex + Sync), max_dist: u32) -> Self {
+        let mut options = ExtensionOptions::default();
+        // Typos in very short queries match almost everything
+        options.min_query_len = 3;
+        Self {
+            options,
+            index,
+            max_dist,
+            min_kana_len: 2,
+        }
+    }
+}
+
+impl<'a> Extension<'a> for FuzzyPrefixExtension<'a> {
+    fn run(&self, query: &SuggestionQuery, rel_weight: f64) -> Vec<EngineItem<'a>> {
+        let rel_weight = rel_weight * self.options.weights.total_weight;
+        let rel_calc = RelevanceCalc::new(self.options.weights).with_total_weight(rel_weight);
+
+        let query_len = query.len() as f32;
+
+        let mut out = PrioContainerMax::new(self.options.limit);
+
+        let items =
+            self.index
+                .fuzzy_predictions(&query.query_str, self.options.limit, self.max_dist);
+        for mut item in items {
+            // Relevance gets set to the edit distance by the index
+            let dist = item.get_relevance() as f32 / EDIT_COST as f32;
+            let str_rel = ((1.0 - dist / (query_len + 1.0)) * 1000.0) as u16;
+            rel_calc.apply(&mut item, str_rel);
+            out.insert(item);
+        }
+
+        out.into_iter().map(|i| i.0).collect()
+    }
+
+    #[inline]
+    fn should_run(&self, already_found: usize, query: &SuggestionQuery) -> bool {
+        let min_len = if query.query_str.chars().all(kana::is_kana) {
+            self.min_kana_len
+        } else {
+            self.options.min_query_len
+        };
+        self.options.enabled && already_found < self.options.threshold && query.len() >= min_len
+    }
+
+    #[inline]
+    fn get_options(&self) -> &ExtensionOptions {
+        &self.options
+    }
+
+    #[inline]
+    fn source(&self) -> Source {
+        Source::FuzzyPrefix
+    }
+}
@@ -4,18 +4,20 @@ use super::{Extension, ExtensionOptions};
 use crate::{
     index::KanjiReadingAlign,
     relevance::{item::EngineItem, RelevanceCalc},
-    suggest::query::SuggestionQuery,
+    suggest::{query::SuggestionQuery, result::Source},
 };
 
+/// Finds words by their aligned readings. Accepts mixed kanji/kana prefixes like "食べ" or
+/// "日ほん" for words that were inserted with `JpIndexBulider::insert_aligned`
 #[derive(Clone, Copy)]
 pub struct KanjiAlignExtension<'a> {
     pub options: ExtensionOptions,
-    index: &'a dyn KanjiReadingAlign,
+    index: &'a (dyn KanjiReadingAlign + Sync),
 }
 
 impl<'a> KanjiAlignExtension<'a> {
     /// Create a new Longest-Prefix Extension
-    pub fn new(index: &'a dyn KanjiReadingAlign) -> Self {
+    pub fn new(index: &'a (dyn KanjiReadingAlign + Sync)) -> Self {
         let mut options = ExtensionOptions::default();
         options.weights.freq_weight = 0.01;
         Self { options, index }
@@ -33,8 +35,7 @@ impl<'a> Extension<'a> for KanjiAlignExtension<'a> {
         for mut item in self.index.align_reading(&query.query_str) {
             //item.set_relevance((item.inner().frequency() * 1000.0) as u16);
             let str_rel = item.inner().str_relevance(&query.query_str);
-            let rel = rel_calc.calc(&item, str_rel);
-            item.set_relevance(rel);
+            rel_calc.apply(&mut item, str_rel);
             out.insert(item);
         }
 
@@ -54,4 +55,9 @@ impl<'a> Extension<'a> for KanjiAlignExtension<'a> {
     fn get_options(&self) -> &ExtensionOptions {
         &self.options
     }
+
+    #[inline]
+    fn source(&self) -> Source {
+        Source::KanjiAlign
+    }
 }
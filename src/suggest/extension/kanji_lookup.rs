@@ -0,0 +1,72 @@
+use priority_container::PrioContainerMax;
+
+use super::{Extension, ExtensionOptions};
+use crate::{
+    index::{
+        kanji::{KanjiFilter, KanjiIndex},
+        SuggestionIndex,
+    },
+    relevance::{item::EngineItem, RelevanceCalc},
+    suggest::{query::SuggestionQuery, result::Source},
+};
+
+/// Finds kanji by radicals, stroke count, JLPT level or grade. Kanji matching the query by
+/// meaning or reading are ranked first
+#[derive(Clone)]
+pub struct KanjiLookupExtension<'a> {
+    pub options: ExtensionOptions,
+    index: &'a KanjiIndex,
+    pub filter: KanjiFilter,
+}
+
+impl<'a> KanjiLookupExtension<'a> {
+    /// Create a new Kanji-Lookup Extension
+    pub fn new(index: &'a KanjiIndex, filter: KanjiFilter) -> Self {
+        let options = ExtensionOptions::default();
+        Self {
+            options,
+            index,
+            filter,
+        }
+    }
+}
+
+impl<'a> Extension<'a> for KanjiLookupExtension<'a> {
+    fn run(&self, query: &SuggestionQuery, rel_weight: f64) -> Vec<EngineItem<'a>> {
+        let rel_weight = rel_weight * self.options.weights.total_weight;
+        let rel_calc = RelevanceCalc::new(self.options.weights).with_total_weight(rel_weight);
+
+        let mut out = PrioContainerMax::new(self.options.limit);
+
+        // Filter the whole index, the query only affects the ranking
+        for mut item in self.index.find(&self.filter, self.index.len()) {
+            let str_rel = if query.query_str.is_empty() {
+                1000
+            } else {
+                item.inner().str_relevance(&query.query_str)
+            };
+            rel_calc.apply(&mut item, str_rel);
+            out.insert(item);
+        }
+
+        out.into_iter().map(|i| i.0).collect()
+    }
+
+    #[inline]
+    fn should_run(&self, already_found: usize, query: &SuggestionQuery) -> bool {
+        self.options.enabled
+            && already_found < self.options.threshold
+            && query.len() >= self.options.min_query_len
+            && !self.filter.is_empty()
+    }
+
+    #[inline]
+    fn get_options(&self) -> &ExtensionOptions {
+        &self.options
+    }
+
+    #[inline]
+    fn source(&self) -> Source {
+        Source::KanjiLookup
+    }
+}
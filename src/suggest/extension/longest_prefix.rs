@@ -6,20 +6,24 @@ use super::{Extension, ExtensionOptions};
 use crate::{
     index::SuggestionIndex,
     relevance::{item::EngineItem, RelevanceCalc},
-    suggest::query::SuggestionQuery,
+    suggest::{query::SuggestionQuery, result::Source},
 };
 
 #[derive(Clone, Copy)]
 pub struct LongestPrefixExtension<'a> {
     pub options: ExtensionOptions,
-    index: &'a dyn SuggestionIndex,
+    index: &'a (dyn SuggestionIndex + Sync),
     pub min_w_len: usize,
     pub max_steps: usize,
 }
 
 impl<'a> LongestPrefixExtension<'a> {
     /// Create a new Longest-Prefix Extension
-    pub fn new(index: &'a dyn SuggestionIndex, min_w_len: usize, max_steps: usize) -> Self {
+    pub fn new(
+        index: &'a (dyn SuggestionIndex + Sync),
+        min_w_len: usize,
+        max_steps: usize,
+    ) -> Self {
         let options = ExtensionOptions::default();
         Self {
             options,
@@ -99,6 +103,11 @@ impl<'a> Extension<'a> for LongestPrefixExtension<'a> {
     fn get_options(&self) -> &ExtensionOptions {
         &self.options
     }
+
+    #[inline]
+    fn source(&self) -> Source {
+        Source::LongestPrefix
+    }
 }
 
 /// Returns a substring of `inp` with `len` amount of tailing characters being removed.
@@ -1,10 +1,16 @@
+pub mod context;
 pub mod custom;
+pub mod deinflect;
+pub mod fuzzy_prefix;
 pub mod kanji_align;
+pub mod kanji_lookup;
 pub mod longest_prefix;
 pub mod ngram;
+pub mod romaji;
 pub mod similar_terms;
 
 use super::query::SuggestionQuery;
+use super::result::Source;
 use crate::relevance::{item::EngineItem, RelevanceCalc, RelevanceWeights};
 
 /// Trait to allow exteding suggestion tasks with custom functionality
@@ -13,6 +19,12 @@ pub trait Extension<'a> {
     fn should_run(&self, already_found: usize, query: &SuggestionQuery) -> bool;
     fn get_options(&self) -> &ExtensionOptions;
 
+    /// Stage reported for items found by this extension
+    #[inline]
+    fn source(&self) -> Source {
+        Source::Custom
+    }
+
     #[inline]
     fn relevance(&self, item: &EngineItem, str_rel: u16) -> u16 {
         let weights = self.get_options().weights;
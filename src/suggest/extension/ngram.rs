@@ -3,7 +3,7 @@ use priority_container::PrioContainerMax;
 use crate::{
     index::NGIndexable,
     relevance::{item::EngineItem, RelevanceCalc},
-    suggest::query::SuggestionQuery,
+    suggest::{query::SuggestionQuery, result::Source},
 };
 
 use super::{Extension, ExtensionOptions};
@@ -13,16 +13,16 @@ pub struct NGramExtension<'a> {
     pub sim_threshold: u16,
     pub query_weigth: f32,
     pub term_limit: usize,
-    index: &'a dyn NGIndexable,
+    index: &'a (dyn NGIndexable + Sync),
     pub cust_query: Option<String>,
 }
 
 impl<'a> NGramExtension<'a> {
-    pub fn new(index: &'a dyn NGIndexable) -> Self {
+    pub fn new(index: &'a (dyn NGIndexable + Sync)) -> Self {
         Self::with_sim_threshold(index, 0.45)
     }
 
-    pub fn with_sim_threshold(index: &'a dyn NGIndexable, sim_threshold: f32) -> Self {
+    pub fn with_sim_threshold(index: &'a (dyn NGIndexable + Sync), sim_threshold: f32) -> Self {
         let mut options = ExtensionOptions::default();
         options.threshold = 10;
         Self {
@@ -64,7 +64,7 @@ impl<'a> Extension<'a> for NGramExtension<'a> {
             if str_rel < self.sim_threshold {
                 continue;
             }
-            item.set_relevance(rel_calc.calc(&item, str_rel));
+            rel_calc.apply(&mut item, str_rel);
             out.insert(item);
         }
 
@@ -84,4 +84,9 @@ impl<'a> Extension<'a> for NGramExtension<'a> {
     fn get_options(&self) -> &ExtensionOptions {
         &self.options
     }
+
+    #[inline]
+    fn source(&self) -> Source {
+        Source::Ngram
+    }
 }
@@ -0,0 +1,79 @@
+use std::collections::HashSet;
+
+use priority_container::PrioContainerMax;
+
+use super::{Extension, ExtensionOptions};
+use crate::{
+    index::SuggestionIndex,
+    normalize::romaji,
+    relevance::{item::EngineItem, RelevanceCalc},
+    suggest::{query::SuggestionQuery, result::Source},
+};
+
+/// Completes romaji input by converting it into kana. A dangling consonant at the end of
+/// the query gets expanded to all kana it can start
+#[derive(Clone, Copy)]
+pub struct RomajiExtension<'a> {
+    pub options: ExtensionOptions,
+    index: &'a (dyn SuggestionIndex + Sync),
+    /// Penalty of the string relevance for items found with an expanded partial syllable
+    pub partial_penalty: u16,
+}
+
+impl<'a> RomajiExtension<'a> {
+    /// Create a new Romaji Extension
+    pub fn new(index: &'a (dyn SuggestionIndex + Sync)) -> Self {
+        let options = ExtensionOptions::default();
+        Self {
+            options,
+            index,
+            partial_penalty: 50,
+        }
+    }
+}
+
+impl<'a> Extension<'a> for RomajiExtension<'a> {
+    fn run(&self, query: &SuggestionQuery, rel_weight: f64) -> Vec<EngineItem<'a>> {
+        let rel_weight = rel_weight * self.options.weights.total_weight;
+        let rel_calc = RelevanceCalc::new(self.options.weights).with_total_weight(rel_weight);
+
+        let mut out = PrioContainerMax::new(self.options.limit);
+        let mut found = HashSet::with_capacity(self.options.limit);
+
+        for prefix in romaji::romaji_prefixes(&query.query_str) {
+            for mut item in self.index.predictions(&prefix.kana, self.options.limit) {
+                if !found.insert(item) {
+                    continue;
+                }
+
+                // Rank by the kana the romaji stands for, not by the romaji itself
+                let mut str_rel = item.inner().str_relevance(&prefix.kana);
+                if prefix.partial {
+                    str_rel = str_rel.saturating_sub(self.partial_penalty);
+                }
+                rel_calc.apply(&mut item, str_rel);
+                out.insert(item);
+            }
+        }
+
+        out.into_iter().map(|i| i.0).collect()
+    }
+
+    #[inline]
+    fn should_run(&self, already_found: usize, query: &SuggestionQuery) -> bool {
+        self.options.enabled
+            && already_found < self.options.threshold
+            && query.len() >= self.options.min_query_len
+            && romaji::is_romaji(&query.query_str)
+    }
+
+    #[inline]
+    fn get_options(&self) -> &ExtensionOptions {
+        &self.options
+    }
+
+    #[inline]
+    fn source(&self) -> Source {
+        Source::Romaji
+    }
+}
@@ -5,19 +5,19 @@ use crate::{
     fast_str_diff::FastStringDist,
     index::SuggestionIndex,
     relevance::{item::EngineItem, RelevanceCalc},
-    suggest::query::SuggestionQuery,
+    suggest::{query::SuggestionQuery, result::Source},
 };
 
 #[derive(Clone, Copy)]
 pub struct SimilarTermsExtension<'a> {
     pub options: ExtensionOptions,
-    index: &'a dyn SuggestionIndex,
+    index: &'a (dyn SuggestionIndex + Sync),
     pub max_str_dist: u32,
 }
 
 impl<'a> SimilarTermsExtension<'a> {
     /// Create a new Longest-Prefix Extension
-    pub fn new(index: &'a dyn SuggestionIndex, max_str_dist: u32) -> Self {
+    pub fn new(index: &'a (dyn SuggestionIndex + Sync), max_str_dist: u32) -> Self {
         let options = ExtensionOptions::default();
         Self {
             options,
@@ -40,7 +40,7 @@ impl<'a> Extension<'a> for SimilarTermsExtension<'a> {
         let out = FastStringDist::new(similar, &query.query_str).assign_mut(|item, query| {
             let str_rel =
                 (item.inner().str_relevance(query) + 100).saturating_sub(item.get_relevance());
-            item.set_relevance(rel_calc.calc(item, str_rel));
+            rel_calc.apply(item, str_rel);
         });
         let mut out_pq = PrioContainerMax::new(self.options.limit);
         out_pq.extend(out);
@@ -58,4 +58,9 @@ impl<'a> Extension<'a> for SimilarTermsExtension<'a> {
     fn get_options(&self) -> &ExtensionOptions {
         &self.options
     }
+
+    #[inline]
+    fn source(&self) -> Source {
+        Source::SimilarTerms
+    }
 }
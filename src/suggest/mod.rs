@@ -4,5 +4,17 @@ pub mod task;
 /// A single query for a suggestion task
 pub mod query;
 
+/// Reusable suggestion tasks
+pub mod pipeline;
+
+/// Declarative pipeline configuration
+pub mod spec;
+
+/// Per-user boosting of selected words
+pub mod personalize;
+
+/// Suggestions with information about how they were found
+pub mod result;
+
 /// Exten an queries behaivor with custom code
 pub mod extension;
@@ -0,0 +1,291 @@
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    sync::{PoisonError, RwLock},
+};
+
+use serde::{Deserialize, Serialize};
+
+use super::result::{Source, SuggestionResult};
+use crate::{
+    index::{IndexItem, SuggestionIndex},
+    relevance::{
+        item::EngineItem,
+        selection::{self, Decayed, DEFAULT_HALF_LIFE, DEFAULT_MAX_BOOST},
+        RelevanceCalc, RelevanceWeights,
+    },
+};
+
+/// Default amount of words remembered per user
+const DEFAULT_CAPACITY: usize = 200;
+
+/// Words a single user selected recently or frequently. Holds at most `capacity` words and
+/// forgets the least used ones first
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct UserProfile {
+    entries: Vec<UsageEntry>,
+    capacity: usize,
+}
+
+/// Usage of a single word by a user
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct UsageEntry {
+    pub word_id: u32,
+    /// Term of the word that got selected
+    pub key: String,
+    /// Uses of the word, decaying the same way as selections of a `SelectionLog`
+    pub uses: Decayed,
+}
+
+impl UsageEntry {
+    /// Returns the weight of the entry at `time`
+    #[inline]
+    pub fn weight(&self, time: u64) -> f64 {
+        self.uses.value_at(time, DEFAULT_HALF_LIFE)
+    }
+}
+
+impl UserProfile {
+    /// Create a new empty profile which remembers up to `capacity` words
+    #[inline]
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: vec![],
+            capacity,
+        }
+    }
+
+    /// Records that the user selected the word `word_id` through its term `key`
+    #[inline]
+    pub fn record(&mut self, word_id: u32, key: &str) {
+        self.record_at(word_id, key, selection::now());
+    }
+
+    /// Records a selection of the user that happened at the given unix timestamp
+    pub fn record_at(&mut self, word_id: u32, key: &str, time: u64) {
+        if let Some(entry) = self.entries.iter_mut().find(|i| i.word_id == word_id) {
+            entry.uses.add(time, DEFAULT_HALF_LIFE);
+            entry.key = key.to_string();
+            return;
+        }
+
+        let mut uses = Decayed::default();
+        uses.add(time, DEFAULT_HALF_LIFE);
+        self.entries.push(UsageEntry {
+            word_id,
+            key: key.to_string(),
+            uses,
+        });
+
+        while self.entries.len() > self.capacity {
+            let least_used = self
+                .entries
+                .iter()
+                .enumerate()
+                .min_by(|a, b| a.1.weight(time).total_cmp(&b.1.weight(time)))
+                .map(|i| i.0);
+            match least_used {
+                Some(pos) => self.entries.remove(pos),
+                None => break,
+            };
+        }
+    }
+
+    /// Records a selected suggestion. Results without a matched key use the primary output
+    pub fn record_result(&mut self, result: &SuggestionResult) {
+        let key = result
+            .matched_key
+            .as_deref()
+            .unwrap_or(&result.output.primary);
+        self.record(result.word_id, key);
+    }
+
+    /// Returns the usage of the word `word_id`
+    #[inline]
+    pub fn get(&self, word_id: u32) -> Option<&UsageEntry> {
+        self.entries.iter().find(|i| i.word_id == word_id)
+    }
+
+    #[inline]
+    pub fn entries(&self) -> &[UsageEntry] {
+        &self.entries
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl Default for UserProfile {
+    #[inline]
+    fn default() -> Self {
+        Self::new(DEFAULT_CAPACITY)
+    }
+}
+
+/// Persistence of user profiles
+pub trait ProfileStore: Send + Sync {
+    type Error;
+
+    /// Loads the profile of `user`. Returns `None` if there is no profile yet
+    fn load(&self, user: &str) -> Result<Option<UserProfile>, Self::Error>;
+
+    fn save(&self, user: &str, profile: &UserProfile) -> Result<(), Self::Error>;
+
+    /// Records a selected suggestion of `user`. The default implementation loads and saves
+    /// the profile, so concurrent selections of the same user may get lost
+    fn record(&self, user: &str, result: &SuggestionResult) -> Result<(), Self::Error> {
+        let mut profile = self.load(user)?.unwrap_or_default();
+        profile.record_result(result);
+        self.save(user, &profile)
+    }
+}
+
+/// Stores user profiles in memory
+#[derive(Debug, Default)]
+pub struct MemoryProfileStore {
+    profiles: RwLock<HashMap<String, UserProfile>>,
+}
+
+impl MemoryProfileStore {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl ProfileStore for MemoryProfileStore {
+    type Error = Infallible;
+
+    fn load(&self, user: &str) -> Result<Option<UserProfile>, Self::Error> {
+        let profiles = self.profiles.read().unwrap_or_else(PoisonError::into_inner);
+        Ok(profiles.get(user).cloned())
+    }
+
+    fn save(&self, user: &str, profile: &UserProfile) -> Result<(), Self::Error> {
+        let mut profiles = self
+            .profiles
+            .write()
+            .unwrap_or_else(PoisonError::into_inner);
+        profiles.insert(user.to_string(), profile.clone());
+        Ok(())
+    }
+
+    fn record(&self, user: &str, result: &SuggestionResult) -> Result<(), Self::Error> {
+        let mut profiles = self
+            .profiles
+            .write()
+            .unwrap_or_else(PoisonError::into_inner);
+        profiles
+            .entry(user.to_string())
+            .or_default()
+            .record_result(result);
+        Ok(())
+    }
+}
+
+/// Personalizes the results of a `SuggestionTask` with the profile of a single user. Words
+/// of the profile get a bounded relevance boost and are added to the results if their key
+/// starts with the query, even if the index didn't return them. Only items of `index` are
+/// affected, so words of other indexes sharing a word ID don't get boosted
+pub struct Personalization<'index> {
+    profile: UserProfile,
+    /// Words of the profile found in the index, ordered by weight descending
+    words: Vec<ProfileWord<'index>>,
+    /// Highest relevance boost a word of the profile can get
+    pub max_boost: u16,
+    /// Max words of the profile added to the results of each query
+    pub inject_limit: usize,
+}
+
+/// A word of the profile together with its item in the index
+struct ProfileWord<'index> {
+    item: EngineItem<'index>,
+    /// Normalized key of the word
+    key: String,
+    weight: f64,
+}
+
+impl<'index> Personalization<'index> {
+    /// Create a new Personalization for `profile`. Words get looked up in `index`
+    pub fn new(index: &'index dyn SuggestionIndex, profile: UserProfile) -> Self {
+        let time = selection::now();
+        let normalizer = index.normalizer();
+
+        let mut words: Vec<_> = profile
+            .entries
+            .iter()
+            .filter_map(|entry| {
+                let key = match normalizer {
+                    Some(normalizer) => normalizer.normalize(&entry.key),
+                    None => entry.key.clone(),
+                };
+                let item = index
+                    .exact(&key)
+                    .into_iter()
+                    .find(|i| i.inner().word_id() == entry.word_id)?;
+                Some(ProfileWord {
+                    item,
+                    key,
+                    weight: entry.weight(time),
+                })
+            })
+            .collect();
+        words.sort_by(|a, b| b.weight.total_cmp(&a.weight));
+
+        Self {
+            profile,
+            words,
+            max_boost: DEFAULT_MAX_BOOST,
+            inject_limit: 3,
+        }
+    }
+
+    #[inline]
+    pub fn profile(&self) -> &UserProfile {
+        &self.profile
+    }
+
+    /// Returns the relevance boost of `item`. Items that aren't part of the index of the
+    /// personalization get no boost
+    pub fn boost(&self, item: &EngineItem) -> u16 {
+        let word_id = item.inner().word_id();
+        self.words
+            .iter()
+            .find(|i| {
+                i.item.inner().word_id() == word_id && same_item(*i.item.inner(), *item.inner())
+            })
+            .map(|i| selection::relevance_boost(i.weight, self.max_boost))
+            .unwrap_or(0)
+    }
+
+    /// Returns the words of the profile whose key starts with the normalized `query`
+    pub(crate) fn inject(&self, query: &str, weights: RelevanceWeights) -> Vec<EngineItem<'index>> {
+        if query.is_empty() {
+            return vec![];
+        }
+
+        let rel_calc = RelevanceCalc::new(weights);
+        self.words
+            .iter()
+            .filter(|i| i.key.starts_with(query))
+            .take(self.inject_limit)
+            .map(|word| {
+                let mut item = word.item;
+                let str_rel = item.inner().str_relevance(query);
+                rel_calc.apply(&mut item, str_rel);
+                item.with_source(Source::Personalized)
+            })
+            .collect()
+    }
+}
+
+/// Returns `true` if both references point to the same item
+#[inline]
+fn same_item(a: &dyn IndexItem, b: &dyn IndexItem) -> bool {
+    std::ptr::eq(
+        a as *const dyn IndexItem as *const u8,
+        b as *const dyn IndexItem as *const u8,
+    )
+}
@@ -0,0 +1,168 @@
+use super::{
+    extension::Extension,
+    query::SuggestionQuery,
+    result::SuggestionResult,
+    task::{Collector, ItemFilter, RelMod},
+};
+use crate::{
+    index::{priority::PriorityFilter, IndexItem, Output, SuggestionIndex},
+    relevance::{item::EngineItem, RelevanceWeights},
+};
+
+/// Definition of a suggestion query which gets executed for each input of a
+/// `SuggestionPipeline`
+pub struct QueryTemplate<'index> {
+    index: &'index (dyn SuggestionIndex + Sync),
+    pub weights: RelevanceWeights,
+    /// Max items allowed to be already found in order for this
+    /// Query to run
+    pub threshold: usize,
+    extensions: Vec<Box<dyn Extension<'index> + Send + Sync + 'index>>,
+}
+
+impl<'index> QueryTemplate<'index> {
+    /// Create a new QueryTemplate for queries on `index`
+    pub fn new(index: &'index (dyn SuggestionIndex + Sync)) -> Self {
+        Self {
+            index,
+            weights: RelevanceWeights::default(),
+            threshold: 0,
+            extensions: vec![],
+        }
+    }
+
+    /// Adds an extension to the query
+    pub fn add_extension<E: Extension<'index> + Send + Sync + 'index>(&mut self, extension: E) {
+        self.extensions.push(Box::new(extension));
+    }
+
+    /// Adds an already boxed extension to the query
+    pub(crate) fn add_boxed_extension(
+        &mut self,
+        extension: Box<dyn Extension<'index> + Send + Sync + 'index>,
+    ) {
+        self.extensions.push(extension);
+    }
+
+    /// Runs the query for `inp`
+    fn search(&self, inp: &str, limit: usize, collector: &mut Collector) {
+        let mut query = SuggestionQuery::new(self.index, inp);
+        query.weights = self.weights;
+        query.threshold = self.threshold;
+
+        let extensions = self
+            .extensions
+            .iter()
+            .map(|i| i.as_ref() as &dyn Extension<'index>);
+        let res = query.search_with(limit, extensions);
+        collector.add(res, &query.query_str, query.weights.total_weight);
+    }
+}
+
+/// A suggestion task that gets built once and executed for each input. Pipelines are
+/// `Send + Sync` and can be shared across threads, eg. within an `Arc`. Because of this,
+/// their indexes, extensions, filters and relevance modifiers have to be `Sync` as well.
+pub struct SuggestionPipeline<'index> {
+    queries: Vec<QueryTemplate<'index>>,
+    limit: usize,
+    explain: bool,
+    romaji: bool,
+    filter: Option<Box<dyn Fn(&dyn IndexItem) -> bool + Send + Sync + 'index>>,
+    rel_mod: Option<Box<dyn Fn(&EngineItem, u16) -> u16 + Send + Sync + 'index>>,
+}
+
+impl<'index> SuggestionPipeline<'index> {
+    /// Create a new SuggestionPipeline with the given output item limit
+    pub fn new(limit: usize) -> Self {
+        Self {
+            queries: vec![],
+            limit,
+            explain: false,
+            romaji: false,
+            filter: None,
+            rel_mod: None,
+        }
+    }
+
+    /// Sets a relevance modifier for output items
+    pub fn set_rel_mod<F: Fn(&EngineItem, u16) -> u16 + Send + Sync + 'index>(
+        &mut self,
+        rel_mod: F,
+    ) {
+        self.rel_mod = Some(Box::new(rel_mod))
+    }
+
+    /// Sets a filter for output items
+    pub fn set_filter<F: Fn(&dyn IndexItem) -> bool + Send + Sync + 'index>(&mut self, filter: F) {
+        self.filter = Some(Box::new(filter))
+    }
+
+    /// Only returns items whose priority matches `filter`. Replaces the filter set with
+    /// `set_filter`. Items without priority data get dropped unless the filter has
+    /// `keep_unknown` set
+    pub fn set_priority_filter(&mut self, filter: PriorityFilter) {
+        self.set_filter(move |item| filter.matches(&item.priority()))
+    }
+
+    /// Adds a query to the pipeline
+    pub fn add_query(&mut self, query: QueryTemplate<'index>) {
+        self.queries.push(query);
+    }
+
+    /// Adds a `ScoreExplanation` to each result of `search_results`
+    pub fn explain(mut self) -> Self {
+        self.explain = true;
+        self
+    }
+
+    /// Adds the Hepburn romaji of the reading to each result written in kana
+    pub fn romaji(mut self) -> Self {
+        self.romaji = true;
+        self
+    }
+
+    /// Performs the suggestion search for `inp`
+    pub fn search(&self, inp: &str) -> Vec<Output> {
+        self.run(inp, false).into_iter().map(|i| i.output).collect()
+    }
+
+    /// Performs the suggestion search for `inp` and returns the suggestions together with
+    /// their relevance, source and matched key
+    #[inline]
+    pub fn search_results(&self, inp: &str) -> Vec<SuggestionResult> {
+        self.run(inp, true)
+    }
+
+    fn run(&self, inp: &str, highlight: bool) -> Vec<SuggestionResult> {
+        let mut collector = Collector::new(
+            self.limit,
+            self.explain,
+            self.filter.as_deref().map(|f| f as &ItemFilter),
+            self.rel_mod.as_deref().map(|f| f as &RelMod),
+        );
+        collector.set_highlight(highlight);
+        collector.set_romaji(self.romaji);
+
+        for template in &self.queries {
+            if !collector.should_run(template.threshold) {
+                continue;
+            }
+
+            template.search(inp, self.limit, &mut collector);
+        }
+
+        collector.finish()
+    }
+
+    /// Returns the amount of queries
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.queries.len()
+    }
+
+    /// Returns `true` if there is no query added to the pipeline
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
@@ -6,7 +6,7 @@ use crate::{
     relevance::{item::EngineItem, RelevanceCalc, RelevanceWeights},
 };
 
-use super::extension::Extension;
+use super::{extension::Extension, result::Source};
 
 pub struct SuggestionQuery<'index, 'ext> {
     /// Index to use for the search
@@ -22,11 +22,16 @@ pub struct SuggestionQuery<'index, 'ext> {
 }
 
 impl<'index, 'ext> SuggestionQuery<'index, 'ext> {
-    /// Create a new suggestion query
+    /// Create a new suggestion query. The query gets normalized with the normalizer of `index`
     pub fn new<S: ToString>(index: &'index dyn SuggestionIndex, query_str: S) -> Self {
+        let mut query_str = query_str.to_string();
+        if let Some(normalizer) = index.normalizer() {
+            query_str = normalizer.normalize(&query_str);
+        }
+
         Self {
             index,
-            query_str: query_str.to_string(),
+            query_str,
             weights: RelevanceWeights::default(),
             threshold: 0,
             extensions: vec![],
@@ -38,24 +43,54 @@ impl<'index, 'ext> SuggestionQuery<'index, 'ext> {
         self.extensions.push(Box::new(extension));
     }
 
+    /// Adds an already boxed extension to the query
+    pub(crate) fn add_boxed_extension(&mut self, extension: Box<dyn Extension<'index> + 'ext>) {
+        self.extensions.push(extension);
+    }
+
     /// Executes the query
     pub fn search(&self, limit: usize) -> Vec<EngineItem<'index>> {
+        let extensions = self
+            .extensions
+            .iter()
+            .map(|i| i.as_ref() as &dyn Extension<'index>);
+        self.search_with(limit, extensions)
+    }
+
+    /// Executes the query with the given extensions instead of the ones added to the query
+    pub(crate) fn search_with<'e, I>(&self, limit: usize, extensions: I) -> Vec<EngineItem<'index>>
+    where
+        I: IntoIterator<Item = &'e dyn Extension<'index>>,
+        'index: 'e,
+    {
         let predictions = self.index.predictions(&self.query_str, limit);
         let mut pred_len = predictions.len();
 
         let pred_ordered = self.order_items(predictions, RelevanceCalc::new(self.weights));
 
         let mut queue = UniquePrioContainerMax::new(limit);
-        queue.extend(pred_ordered);
+        queue.extend(
+            pred_ordered
+                .into_iter()
+                .map(|i| i.with_source(Source::Prediction)),
+        );
 
-        for extension in &self.extensions {
+        for extension in extensions {
             if !extension.should_run(pred_len, &self) {
                 continue;
             }
 
             let ext_res = extension.run(&self, self.weights.total_weight);
             pred_len += ext_res.len();
-            queue.extend(ext_res);
+
+            // Keep sources set by the extension itself
+            let source = extension.source();
+            queue.extend(ext_res.into_iter().map(|mut i| {
+                if i.source() == Source::Custom {
+                    i.set_source(source);
+                }
+                i
+            }));
         }
 
         queue.into_iter().collect::<Vec<_>>()
@@ -77,7 +112,7 @@ impl<'index, 'ext> SuggestionQuery<'index, 'ext> {
             if str_rel == 0 {
                 str_rel = item.inner().str_relevance(query);
             }
-            item.set_relevance(rel_calc.calc(item, str_rel));
+            rel_calc.apply(item, str_rel);
         })
     }
 }
@@ -0,0 +1,180 @@
+use std::ops::Range;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    index::{japanese::deinflect::Inflections, Output},
+    normalize::fold,
+    relevance::{item::EngineItem, ScoreExplanation},
+};
+
+/// Stage of a suggestion task that produced an item
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum Source {
+    Prediction,
+    LongestPrefix,
+    SimilarTerms,
+    Ngram,
+    KanjiAlign,
+    FuzzyPrefix,
+    /// Custom extensions and entries
+    #[default]
+    Custom,
+    // Variants are serialized by position, so new ones have to be appended
+    /// Romaji input converted to kana
+    Romaji,
+    /// Dictionary form of an inflected query, holding the inflections that were removed
+    Deinflection(Inflections),
+    /// Kanji found by radicals, stroke count or level
+    KanjiLookup,
+    /// Word from the profile of the user that wasn't found by a query
+    Personalized,
+    /// Word predicted from the preceding tokens of the query
+    Context,
+}
+
+/// A single suggestion together with information about why it was found. Results don't
+/// borrow from the index, so they can be cached, sent to other threads or serialized
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SuggestionResult {
+    pub word_id: u32,
+    /// Final relevance of the suggestion
+    pub relevance: u16,
+    pub source: Source,
+    pub output: Output,
+    /// Term of the item that matched the query
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub matched_key: Option<String>,
+    /// Byte ranges within `matched_key` that match the query
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub highlight: Vec<Range<usize>>,
+    /// Components of the relevance. Only set if the task runs in explain mode
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub explanation: Option<ScoreExplanation>,
+}
+
+impl SuggestionResult {
+    /// Create a new SuggestionResult for an item found with `query`, including the matched key
+    /// and its highlighted ranges
+    pub fn new(item: &EngineItem, query: &str) -> Self {
+        let mut res = Self::without_highlight(item);
+        let query: Vec<char> = query.chars().flat_map(fold::fold_char).collect();
+
+        let mut matched_len = 0;
+        for term in item.inner().terms() {
+            let ranges = highlight_folded(term, &query);
+            let len: usize = ranges.iter().map(|i| i.len()).sum();
+            if len > matched_len {
+                matched_len = len;
+                res.matched_key = Some(term.to_string());
+                res.highlight = ranges;
+            }
+        }
+
+        res
+    }
+
+    /// Create a new SuggestionResult without looking up the matched key
+    pub fn without_highlight(item: &EngineItem) -> Self {
+        Self {
+            word_id: item.inner().word_id(),
+            relevance: item.get_relevance(),
+            source: item.source(),
+            output: item.to_output(),
+            matched_key: None,
+            highlight: vec![],
+            explanation: None,
+        }
+    }
+}
+
+/// Returns the byte ranges of `term` that match `query`. Case, diacritics and the kana script
+/// get ignored. A full occurrence of `query` is preferred, otherwise the longest matching
+/// prefix of `term` gets returned
+pub fn highlight_ranges(term: &str, query: &str) -> Vec<Range<usize>> {
+    let query: Vec<char> = query.chars().flat_map(fold::fold_char).collect();
+    highlight_folded(term, &query)
+}
+
+/// Same as `highlight_ranges` but takes an already folded query
+fn highlight_folded(term: &str, query: &[char]) -> Vec<Range<usize>> {
+    if query.is_empty() {
+        return vec![];
+    }
+
+    // Folded characters of the term together with the byte range of their source character
+    let folded: Vec<(char, Range<usize>)> = term
+        .char_indices()
+        .flat_map(|(pos, c)| fold::fold_char(c).map(move |f| (f, pos..pos + c.len_utf8())))
+        .collect();
+
+    let mut prefix = None;
+    for start in 0..folded.len() {
+        // Only start at the first folded character of a source character
+        if start > 0 && folded[start - 1].1 == folded[start].1 {
+            continue;
+        }
+
+        let matched = folded[start..]
+            .iter()
+            .zip(query)
+            .take_while(|(f, q)| f.0 == **q)
+            .count();
+        if matched == 0 {
+            continue;
+        }
+
+        let range = folded[start].1.start..folded[start + matched - 1].1.end;
+        if matched == query.len() {
+            return vec![range];
+        }
+        if start == 0 {
+            prefix = Some(range);
+        }
+    }
+
+    prefix.into_iter().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_multi_char_fold() {
+        // "ß" folds to "ss" and is always highlighted as a whole
+        assert_eq!(highlight_ranges("Straße", "strasse"), vec![0..7]);
+        assert_eq!(highlight_ranges("Straße", "stras"), vec![0..6]);
+        assert_eq!(highlight_ranges("Straße", "sse"), vec![4..7]);
+        // Matches can't start at the second character of a fold
+        assert_eq!(highlight_ranges("Straße", "se"), vec![0..1]);
+    }
+
+    #[test]
+    fn test_diacritics() {
+        assert_eq!(highlight_ranges("Müller", "mull"), vec![0..5]);
+        assert_eq!(highlight_ranges("Müller", "MÜLL"), vec![0..5]);
+        assert_eq!(highlight_ranges("Muller", "mül"), vec![0..3]);
+    }
+
+    #[test]
+    fn test_kana_script() {
+        assert_eq!(highlight_ranges("カタカナ", "かな"), vec![6..12]);
+        assert_eq!(highlight_ranges("ガッコウ", "か"), vec![0..3]);
+    }
+
+    #[test]
+    fn test_full_match_within_term() {
+        assert_eq!(highlight_ranges("New York", "york"), vec![4..8]);
+        // A full match is preferred over a partial match at the start
+        assert_eq!(highlight_ranges("new news", "news"), vec![4..8]);
+    }
+
+    #[test]
+    fn test_partial_prefix() {
+        assert_eq!(highlight_ranges("apple", "apricot"), vec![0..2]);
+        // Partial matches only count at the start of the term
+        assert!(highlight_ranges("pineapple", "apricot").is_empty());
+        assert!(highlight_ranges("apple", "").is_empty());
+    }
+}
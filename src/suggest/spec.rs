@@ -0,0 +1,715 @@
+use std::{collections::HashMap, fmt::Display};
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    extension::{
+        context::ContextExtension, deinflect::DeinflectExtension,
+        fuzzy_prefix::FuzzyPrefixExtension, kanji_align::KanjiAlignExtension,
+        kanji_lookup::KanjiLookupExtension, longest_prefix::LongestPrefixExtension,
+        ngram::NGramExtension, romaji::RomajiExtension, similar_terms::SimilarTermsExtension,
+        Extension, ExtensionOptions,
+    },
+    pipeline::{QueryTemplate, SuggestionPipeline},
+    query::SuggestionQuery,
+    task::SuggestionTask,
+};
+use crate::{
+    index::{
+        kanji::{KanjiFilter, KanjiIndex},
+        language_model::LanguageModel,
+        KanjiReadingAlign, NGIndexable, SuggestionIndex,
+    },
+    relevance::RelevanceWeights,
+};
+
+/// Declarative definition of a suggestion pipeline which can be loaded from TOML or JSON
+///
+/// ```toml
+/// limit = 30
+///
+/// [[queries]]
+/// index = "words"
+/// weights = { str_weight = 1.4, freq_weight = 0.6 }
+///
+/// [[queries.extensions]]
+/// type = "ngram"
+/// query_weight = 0.6
+/// options = { threshold = 10 }
+/// ```
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct PipelineSpec {
+    /// Max amount of results
+    pub limit: usize,
+    #[serde(default)]
+    pub explain: bool,
+    /// Adds the romaji of the reading to each result
+    #[serde(default)]
+    pub romaji: bool,
+    pub queries: Vec<QuerySpec>,
+}
+
+/// Definition of a single query
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct QuerySpec {
+    /// Name of the index in the `IndexRegistry`
+    pub index: String,
+    #[serde(default)]
+    pub weights: RelevanceWeights,
+    #[serde(default)]
+    pub threshold: usize,
+    /// Extensions in the order they get run
+    #[serde(default)]
+    pub extensions: Vec<ExtensionSpec>,
+}
+
+/// Definition of an extension. Unset options keep the extensions defaults
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ExtensionSpec {
+    LongestPrefix {
+        #[serde(default)]
+        options: OptionsSpec,
+        #[serde(default)]
+        min_w_len: usize,
+        #[serde(default = "default_max_steps")]
+        max_steps: usize,
+    },
+    SimilarTerms {
+        #[serde(default)]
+        options: OptionsSpec,
+        max_str_dist: u32,
+    },
+    Ngram {
+        #[serde(default)]
+        options: OptionsSpec,
+        sim_threshold: Option<f32>,
+        query_weight: Option<f32>,
+        term_limit: Option<usize>,
+    },
+    KanjiAlign {
+        #[serde(default)]
+        options: OptionsSpec,
+    },
+    FuzzyPrefix {
+        #[serde(default)]
+        options: OptionsSpec,
+        max_dist: u32,
+        min_kana_len: Option<usize>,
+    },
+    Romaji {
+        #[serde(default)]
+        options: OptionsSpec,
+        partial_penalty: Option<u16>,
+    },
+    Deinflect {
+        #[serde(default)]
+        options: OptionsSpec,
+        #[serde(default)]
+        predict: bool,
+    },
+    /// Requires a kanji index registered with `IndexRegistry::add_kanji`
+    KanjiLookup {
+        #[serde(default)]
+        options: OptionsSpec,
+        #[serde(default)]
+        radicals: Vec<char>,
+        min_strokes: Option<u8>,
+        max_strokes: Option<u8>,
+        jlpt: Option<u8>,
+        grade: Option<u8>,
+    },
+    /// Requires a language model registered with `IndexRegistry::add_language_model`
+    Context {
+        #[serde(default)]
+        options: OptionsSpec,
+        /// Name of the language model. Defaults to the index of the query
+        model: Option<String>,
+    },
+}
+
+/// Overrides for `ExtensionOptions`
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub struct OptionsSpec {
+    pub enabled: Option<bool>,
+    pub limit: Option<usize>,
+    pub threshold: Option<usize>,
+    pub weights: Option<RelevanceWeights>,
+    pub min_query_len: Option<usize>,
+}
+
+#[inline]
+fn default_max_steps() -> usize {
+    5
+}
+
+/// Error returned when loading or building a `PipelineSpec`
+#[derive(Debug)]
+pub enum SpecError {
+    Toml(toml::de::Error),
+    Json(serde_json::Error),
+    /// No index with the given name was registered
+    UnknownIndex(String),
+    /// The index doesn't support the given extension
+    Unsupported {
+        index: String,
+        extension: &'static str,
+    },
+}
+
+impl Display for SpecError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SpecError::Toml(err) => write!(f, "invalid toml: {err}"),
+            SpecError::Json(err) => write!(f, "invalid json: {err}"),
+            SpecError::UnknownIndex(name) => write!(f, "unknown index {name:?}"),
+            SpecError::Unsupported { index, extension } => {
+                write!(
+                    f,
+                    "index {index:?} doesn't support the {extension} extension"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for SpecError {}
+
+impl From<toml::de::Error> for SpecError {
+    #[inline]
+    fn from(err: toml::de::Error) -> Self {
+        Self::Toml(err)
+    }
+}
+
+impl From<serde_json::Error> for SpecError {
+    #[inline]
+    fn from(err: serde_json::Error) -> Self {
+        Self::Json(err)
+    }
+}
+
+/// Indexes that can be referenced by name in a `PipelineSpec`
+#[derive(Default)]
+pub struct IndexRegistry<'a> {
+    indexes: HashMap<String, RegisteredIndex<'a>>,
+}
+
+#[derive(Default)]
+struct RegisteredIndex<'a> {
+    index: Option<&'a (dyn SuggestionIndex + Sync)>,
+    ngram: Option<&'a (dyn NGIndexable + Sync)>,
+    kanji_align: Option<&'a (dyn KanjiReadingAlign + Sync)>,
+    kanji: Option<&'a KanjiIndex>,
+    language_model: Option<&'a LanguageModel>,
+}
+
+impl<'a> IndexRegistry<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers `index` under `name`
+    pub fn add_index<S: ToString>(&mut self, name: S, index: &'a (dyn SuggestionIndex + Sync)) {
+        self.entry(name).index = Some(index);
+    }
+
+    /// Registers the n-gram lookup of the index `name`, used by the ngram extension
+    pub fn add_ngram<S: ToString>(&mut self, name: S, index: &'a (dyn NGIndexable + Sync)) {
+        self.entry(name).ngram = Some(index);
+    }
+
+    /// Registers the kanji reading alignment of the index `name`, used by the kanji_align
+    /// extension
+    pub fn add_kanji_align<S: ToString>(
+        &mut self,
+        name: S,
+        index: &'a (dyn KanjiReadingAlign + Sync),
+    ) {
+        self.entry(name).kanji_align = Some(index);
+    }
+
+    /// Registers `index` under `name` as kanji index, used by the kanji_lookup extension
+    pub fn add_kanji<S: ToString>(&mut self, name: S, index: &'a KanjiIndex) {
+        let entry = self.entry(name);
+        entry.index = Some(index);
+        entry.kanji = Some(index);
+    }
+
+    /// Registers `model` under `name` as language model, used by the context extension
+    pub fn add_language_model<S: ToString>(&mut self, name: S, model: &'a LanguageModel) {
+        let entry = self.entry(name);
+        entry.index = Some(model);
+        entry.language_model = Some(model);
+    }
+
+    #[inline]
+    fn entry<S: ToString>(&mut self, name: S) -> &mut RegisteredIndex<'a> {
+        self.indexes.entry(name.to_string()).or_default()
+    }
+
+    fn get(&self, name: &str) -> Result<&RegisteredIndex<'a>, SpecError> {
+        self.indexes
+            .get(name)
+            .ok_or_else(|| SpecError::UnknownIndex(name.to_string()))
+    }
+
+    fn index(&self, name: &str) -> Result<&'a (dyn SuggestionIndex + Sync), SpecError> {
+        self.get(name)?
+            .index
+            .ok_or_else(|| SpecError::UnknownIndex(name.to_string()))
+    }
+}
+
+impl PipelineSpec {
+    /// Parses a spec from TOML
+    pub fn from_toml(inp: &str) -> Result<Self, SpecError> {
+        Ok(toml::from_str(inp)?)
+    }
+
+    /// Parses a spec from JSON
+    pub fn from_json(inp: &str) -> Result<Self, SpecError> {
+        Ok(serde_json::from_str(inp)?)
+    }
+
+    /// Builds a reusable pipeline out of the spec
+    pub fn build<'a>(
+        &self,
+        indexes: &IndexRegistry<'a>,
+    ) -> Result<SuggestionPipeline<'a>, SpecError> {
+        let mut pipeline = SuggestionPipeline::new(self.limit);
+        if self.explain {
+            pipeline = pipeline.explain();
+        }
+        if self.romaji {
+            pipeline = pipeline.romaji();
+        }
+
+        for spec in &self.queries {
+            let mut query = QueryTemplate::new(indexes.index(&spec.index)?);
+            query.weights = spec.weights;
+            query.threshold = spec.threshold;
+            for ext in &spec.extensions {
+                query.add_boxed_extension(ext.build(&spec.index, indexes)?);
+            }
+            pipeline.add_query(query);
+        }
+
+        Ok(pipeline)
+    }
+
+    /// Builds a task out of the spec that searches for `inp`
+    pub fn build_task<'a>(
+        &self,
+        indexes: &IndexRegistry<'a>,
+        inp: &str,
+    ) -> Result<SuggestionTask<'a, 'a, 'a>, SpecError> {
+        let mut task = SuggestionTask::new(self.limit);
+        if self.explain {
+            task = task.explain();
+        }
+        if self.romaji {
+            task = task.romaji();
+        }
+
+        for spec in &self.queries {
+            let mut query = SuggestionQuery::new(indexes.index(&spec.index)?, inp);
+            query.weights = spec.weights;
+            query.threshold = spec.threshold;
+            for ext in &spec.extensions {
+                query.add_boxed_extension(ext.build(&spec.index, indexes)?);
+            }
+            task.add_query(query);
+        }
+
+        Ok(task)
+    }
+}
+
+impl ExtensionSpec {
+    /// Builds the extension for the index `name`
+    fn build<'a>(
+        &self,
+        name: &str,
+        indexes: &IndexRegistry<'a>,
+    ) -> Result<Box<dyn Extension<'a> + Send + Sync + 'a>, SpecError> {
+        let unsupported = |extension| SpecError::Unsupported {
+            index: name.to_string(),
+            extension,
+        };
+
+        Ok(match self {
+            ExtensionSpec::LongestPrefix {
+                options,
+                min_w_len,
+                max_steps,
+            } => {
+                let mut ext =
+                    LongestPrefixExtension::new(indexes.index(name)?, *min_w_len, *max_steps);
+                options.apply(&mut ext.options);
+                Box::new(ext)
+            }
+            ExtensionSpec::SimilarTerms {
+                options,
+                max_str_dist,
+            } => {
+                let mut ext = SimilarTermsExtension::new(indexes.index(name)?, *max_str_dist);
+                options.apply(&mut ext.options);
+                Box::new(ext)
+            }
+            ExtensionSpec::Ngram {
+                options,
+                sim_threshold,
+                query_weight,
+                term_limit,
+            } => {
+                let index = indexes
+                    .get(name)?
+                    .ngram
+                    .ok_or_else(|| unsupported("ngram"))?;
+                let mut ext = match sim_threshold {
+                    Some(t) => NGramExtension::with_sim_threshold(index, *t),
+                    None => NGramExtension::new(index),
+                };
+                if let Some(w) = query_weight {
+                    ext.query_weigth = *w;
+                }
+                if let Some(l) = term_limit {
+                    ext.term_limit = *l;
+                }
+                options.apply(&mut ext.options);
+                Box::new(ext)
+            }
+            ExtensionSpec::KanjiAlign { options } => {
+                let index = indexes
+                    .get(name)?
+                    .kanji_align
+                    .ok_or_else(|| unsupported("kanji_align"))?;
+                let mut ext = KanjiAlignExtension::new(index);
+                options.apply(&mut ext.options);
+                Box::new(ext)
+            }
+            ExtensionSpec::FuzzyPrefix {
+                options,
+                max_dist,
+                min_kana_len,
+            } => {
+                let mut ext = FuzzyPrefixExtension::new(indexes.index(name)?, *max_dist);
+                if let Some(len) = min_kana_len {
+                    ext.min_kana_len = *len;
+                }
+                options.apply(&mut ext.options);
+                Box::new(ext)
+            }
+            ExtensionSpec::Romaji {
+                options,
+                partial_penalty,
+            } => {
+                let mut ext = RomajiExtension::new(indexes.index(name)?);
+                if let Some(penalty) = partial_penalty {
+                    ext.partial_penalty = *penalty;
+                }
+                options.apply(&mut ext.options);
+                Box::new(ext)
+            }
+            ExtensionSpec::Deinflect { options, predict } => {
+                let mut ext = DeinflectExtension::new(indexes.index(name)?);
+                ext.predict = *predict;
+                options.apply(&mut ext.options);
+                Box::new(ext)
+            }
+            ExtensionSpec::KanjiLookup {
+                options,
+                radicals,
+                min_strokes,
+                max_strokes,
+                jlpt,
+                grade,
+            } => {
+                let index = indexes
+                    .get(name)?
+                    .kanji
+                    .ok_or_else(|| unsupported("kanji_lookup"))?;
+                let mut filter = KanjiFilter {
+                    radicals: radicals.clone(),
+                    jlpt: *jlpt,
+                    grade: *grade,
+                    ..KanjiFilter::default()
+                };
+                if min_strokes.is_some() || max_strokes.is_some() {
+                    filter.strokes =
+                        Some(min_strokes.unwrap_or(0)..=max_strokes.unwrap_or(u8::MAX));
+                }
+                let mut ext = KanjiLookupExtension::new(index, filter);
+                options.apply(&mut ext.options);
+                Box::new(ext)
+            }
+            ExtensionSpec::Context { options, model } => {
+                let model = indexes
+                    .get(model.as_deref().unwrap_or(name))?
+                    .language_model
+                    .ok_or_else(|| unsupported("context"))?;
+                let mut ext = ContextExtension::new(model);
+                options.apply(&mut ext.options);
+                Box::new(ext)
+            }
+        })
+    }
+}
+
+impl OptionsSpec {
+    /// Overwrites all set options in `options`
+    pub fn apply(&self, options: &mut ExtensionOptions) {
+        if let Some(enabled) = self.enabled {
+            options.enabled = enabled;
+        }
+        if let Some(limit) = self.limit {
+            options.limit = limit;
+        }
+        if let Some(threshold) = self.threshold {
+            options.threshold = threshold;
+        }
+        if let Some(weights) = self.weights {
+            options.weights = weights;
+        }
+        if let Some(min_query_len) = self.min_query_len {
+            options.min_query_len = min_query_len;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::index::test_util;
+
+    const TOML: &str = r#"
+        limit = 30
+
+        [[queries]]
+        index = "words"
+        weights = { str_weight = 1.4, freq_weight = 0.6 }
+
+        [[queries.extensions]]
+        type = "ngram"
+        query_weight = 0.6
+        options = { threshold = 10 }
+
+        [[queries.extensions]]
+        type = "longest_prefix"
+    "#;
+
+    fn words_spec(extensions: Vec<ExtensionSpec>) -> PipelineSpec {
+        PipelineSpec {
+            limit: 10,
+            explain: false,
+            romaji: false,
+            queries: vec![QuerySpec {
+                index: "words".to_string(),
+                weights: RelevanceWeights::default(),
+                threshold: 0,
+                extensions,
+            }],
+        }
+    }
+
+    /// Spec with each extension type, including set and unset optional fields
+    fn full_spec() -> PipelineSpec {
+        let options = OptionsSpec {
+            enabled: Some(true),
+            limit: Some(20),
+            threshold: Some(5),
+            weights: Some(RelevanceWeights::new(1.2, 0.8, 0.5)),
+            min_query_len: None,
+        };
+        let mut spec = words_spec(vec![
+            ExtensionSpec::LongestPrefix {
+                options,
+                min_w_len: 2,
+                max_steps: 3,
+            },
+            ExtensionSpec::SimilarTerms {
+                options: OptionsSpec::default(),
+                max_str_dist: 7,
+            },
+            ExtensionSpec::Ngram {
+                options,
+                sim_threshold: Some(0.5),
+                query_weight: None,
+                term_limit: Some(100),
+            },
+            ExtensionSpec::KanjiAlign {
+                options: OptionsSpec::default(),
+            },
+            ExtensionSpec::FuzzyPrefix {
+                options,
+                max_dist: 2,
+                min_kana_len: None,
+            },
+            ExtensionSpec::Romaji {
+                options: OptionsSpec::default(),
+                partial_penalty: Some(50),
+            },
+            ExtensionSpec::Deinflect {
+                options,
+                predict: true,
+            },
+            ExtensionSpec::KanjiLookup {
+                options: OptionsSpec::default(),
+                radicals: vec!['口', '木'],
+                min_strokes: Some(2),
+                max_strokes: None,
+                jlpt: Some(5),
+                grade: None,
+            },
+            ExtensionSpec::Context {
+                options,
+                model: Some("model".to_string()),
+            },
+        ]);
+        spec.explain = true;
+        spec.queries[0].weights = RelevanceWeights::new(1.4, 0.6, 1.0);
+        spec.queries[0].threshold = 3;
+        spec
+    }
+
+    #[test]
+    fn test_parse_toml() {
+        let spec = PipelineSpec::from_toml(TOML).unwrap();
+        assert_eq!(spec.limit, 30);
+        assert!(!spec.explain);
+        assert!(!spec.romaji);
+
+        let query = &spec.queries[0];
+        assert_eq!(query.index, "words");
+        assert_eq!(query.threshold, 0);
+        assert_eq!(query.weights.str_weight, 1.4);
+        assert_eq!(query.weights.freq_weight, 0.6);
+        let defaults = RelevanceWeights::default();
+        assert_eq!(query.weights.total_weight, defaults.total_weight);
+        assert_eq!(query.weights.priority_weight, defaults.priority_weight);
+
+        let options = OptionsSpec {
+            threshold: Some(10),
+            ..OptionsSpec::default()
+        };
+        let expected = vec![
+            ExtensionSpec::Ngram {
+                options,
+                sim_threshold: None,
+                query_weight: Some(0.6),
+                term_limit: None,
+            },
+            ExtensionSpec::LongestPrefix {
+                options: OptionsSpec::default(),
+                min_w_len: 0,
+                max_steps: 5,
+            },
+        ];
+        assert_eq!(query.extensions, expected);
+    }
+
+    #[test]
+    fn test_toml_round_trip() {
+        let spec = full_spec();
+        // Values of `toml::Value` tables get emitted before nested tables
+        let toml = toml::Value::try_from(&spec).unwrap().to_string();
+        assert_eq!(PipelineSpec::from_toml(&toml).unwrap(), spec);
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let spec = full_spec();
+        let json = serde_json::to_string(&spec).unwrap();
+        assert_eq!(PipelineSpec::from_json(&json).unwrap(), spec);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let missing_dist = r#"{"limit": 5, "queries": [
+            {"index": "words", "extensions": [{"type": "similar_terms"}]}
+        ]}"#;
+        assert!(matches!(
+            PipelineSpec::from_json(missing_dist),
+            Err(SpecError::Json(_))
+        ));
+
+        let unknown_type = "limit = 5\n[[queries]]\nindex = \"words\"\n\
+            [[queries.extensions]]\ntype = \"unknown\"\n";
+        assert!(matches!(
+            PipelineSpec::from_toml(unknown_type),
+            Err(SpecError::Toml(_))
+        ));
+    }
+
+    #[test]
+    fn test_unknown_index() {
+        let index = test_util::basic_index(&[("apple", 0.5)], |_| ());
+        let mut registry = IndexRegistry::new();
+        registry.add_index("other", &index);
+
+        let spec = words_spec(vec![]);
+        let err = spec.build(&registry).err().unwrap();
+        assert!(matches!(err, SpecError::UnknownIndex(ref name) if name == "words"));
+        let err = spec.build_task(&registry, "app").err().unwrap();
+        assert!(matches!(err, SpecError::UnknownIndex(ref name) if name == "words"));
+
+        // The context extension looks up its model by name
+        let spec = words_spec(vec![ExtensionSpec::Context {
+            options: OptionsSpec::default(),
+            model: Some("model".to_string()),
+        }]);
+        registry.add_index("words", &index);
+        let err = spec.build(&registry).err().unwrap();
+        assert!(matches!(err, SpecError::UnknownIndex(ref name) if name == "model"));
+    }
+
+    #[test]
+    fn test_unsupported_extension() {
+        let index = test_util::basic_index(&[("apple", 0.5)], |_| ());
+        let mut registry = IndexRegistry::new();
+        registry.add_index("words", &index);
+
+        let unsupported = [
+            (
+                ExtensionSpec::Ngram {
+                    options: OptionsSpec::default(),
+                    sim_threshold: None,
+                    query_weight: None,
+                    term_limit: None,
+                },
+                "ngram",
+            ),
+            (
+                ExtensionSpec::KanjiAlign {
+                    options: OptionsSpec::default(),
+                },
+                "kanji_align",
+            ),
+            (
+                ExtensionSpec::Context {
+                    options: OptionsSpec::default(),
+                    model: None,
+                },
+                "context",
+            ),
+        ];
+        for (ext, name) in unsupported {
+            let err = words_spec(vec![ext]).build(&registry).err().unwrap();
+            assert!(matches!(
+                err,
+                SpecError::Unsupported { ref index, extension } if index == "words" && extension == name
+            ));
+        }
+
+        // Registering the n-gram lookup makes the extension available
+        registry.add_ngram("words", &index);
+        let spec = words_spec(vec![ExtensionSpec::Ngram {
+            options: OptionsSpec::default(),
+            sim_threshold: None,
+            query_weight: None,
+            term_limit: None,
+        }]);
+        assert_eq!(spec.build(&registry).unwrap().len(), 1);
+    }
+}
@@ -1,20 +1,30 @@
-use super::query::SuggestionQuery;
+use std::hash::Hash;
+
+use super::{personalize::Personalization, query::SuggestionQuery, result::SuggestionResult};
 use order_struct::order_nh::OrderVal;
 use priority_container::UniquePrioContainerMax;
 
 use crate::{
-    index::{IndexItem, Output},
-    relevance::item::EngineItem,
+    index::{priority::PriorityFilter, IndexItem, Output},
+    relevance::{item::EngineItem, RelevanceCalc, RelevanceWeights, ScoreExplanation},
 };
 
+/// Filter for output items. Items get dropped if it returns `false`
+pub type ItemFilter<'f> = dyn Fn(&dyn IndexItem) -> bool + 'f;
+
+/// Modifies the relevance of output items
+pub type RelMod<'f> = dyn Fn(&EngineItem, u16) -> u16 + 'f;
+
 /// An autocompletion task to run multiple suggestion queries
 pub struct SuggestionTask<'index, 'a, 'ext> {
     queries: Vec<SuggestionQuery<'index, 'ext>>,
     custom_entries: Vec<EngineItem<'a>>,
     limit: usize,
-    debug: bool,
-    filter: Option<Box<dyn Fn(&dyn IndexItem) -> bool + 'ext>>,
-    rel_mod: Option<Box<dyn Fn(&EngineItem, u16) -> u16 + 'ext>>,
+    explain: bool,
+    romaji: bool,
+    filter: Option<Box<ItemFilter<'ext>>>,
+    rel_mod: Option<Box<RelMod<'ext>>>,
+    personalization: Option<Personalization<'index>>,
 }
 
 impl<'index, 'a, 'ext> SuggestionTask<'index, 'a, 'ext> {
@@ -24,13 +34,15 @@ impl<'index, 'a, 'ext> SuggestionTask<'index, 'a, 'ext> {
             queries: vec![],
             limit,
             custom_entries: vec![],
-            debug: false,
+            explain: false,
+            romaji: false,
             filter: None,
             rel_mod: None,
+            personalization: None,
         }
     }
 
-    /// Sets a filter for output items
+    /// Sets a relevance modifier for output items
     pub fn set_rel_mod<F: Fn(&EngineItem, u16) -> u16 + 'ext>(&mut self, rel_mod: F) {
         self.rel_mod = Some(Box::new(rel_mod))
     }
@@ -40,6 +52,18 @@ impl<'index, 'a, 'ext> SuggestionTask<'index, 'a, 'ext> {
         self.filter = Some(Box::new(filter))
     }
 
+    /// Only returns items whose priority matches `filter`, eg. only N5 to N3 words.
+    /// Replaces the filter set with `set_filter`. Items without priority data get dropped
+    /// unless the filter has `keep_unknown` set
+    pub fn set_priority_filter(&mut self, filter: PriorityFilter) {
+        self.set_filter(move |item| filter.matches(&item.priority()))
+    }
+
+    /// Boosts and adds words of a users profile
+    pub fn set_personalization(&mut self, personalization: Personalization<'index>) {
+        self.personalization = Some(personalization);
+    }
+
     // Adds a query to the Task
     pub fn add_query(&mut self, query: SuggestionQuery<'index, 'ext>) {
         self.queries.push(query);
@@ -50,79 +74,275 @@ impl<'index, 'a, 'ext> SuggestionTask<'index, 'a, 'ext> {
         self.custom_entries.extend(entries);
     }
 
-    pub fn debug(mut self) -> Self {
-        self.debug = true;
+    /// Adds a `ScoreExplanation` to each result of `search_results`
+    pub fn explain(mut self) -> Self {
+        self.explain = true;
+        self
+    }
+
+    /// Adds the Hepburn romaji of the reading to each result written in kana
+    pub fn romaji(mut self) -> Self {
+        self.romaji = true;
         self
     }
 
+    #[deprecated(note = "use `explain` and `search_results` instead")]
+    pub fn debug(self) -> Self {
+        self.explain()
+    }
+
     /// Performs the suggestion search
     pub fn search(&self) -> Vec<Output> {
-        let mut out = UniquePrioContainerMax::new_allocated(self.limit);
-        let mut added = 0;
+        self.run(false).into_iter().map(|i| i.output).collect()
+    }
+
+    /// Performs the suggestion search and returns the suggestions together with their
+    /// relevance, source and matched key
+    #[inline]
+    pub fn search_results(&self) -> Vec<SuggestionResult> {
+        self.run(true)
+    }
+
+    fn run(&self, highlight: bool) -> Vec<SuggestionResult> {
+        let mut collector = Collector::new(
+            self.limit,
+            self.explain,
+            self.filter.as_deref(),
+            self.rel_mod.as_deref(),
+        );
+        collector.set_highlight(highlight);
+        collector.set_romaji(self.romaji);
+        if let Some(personalization) = &self.personalization {
+            collector.set_personalization(personalization);
+        }
 
         for query in &self.queries {
-            if added >= query.threshold && query.threshold > 0 {
+            if !collector.should_run(query.threshold) {
                 continue;
             }
 
             let query_res = query.search(self.limit);
+            collector.add(query_res, &query.query_str, query.weights.total_weight);
+        }
 
-            if self.debug {
-                println!("query found {} items", query_res.len());
+        // Added last so they don't count towards the thresholds of the queries
+        if let Some(personalization) = &self.personalization {
+            for query in &self.queries {
+                let items = personalization.inject(&query.query_str, query.weights);
+                collector.add(items, &query.query_str, query.weights.total_weight);
             }
+        }
+
+        collector.add_custom(&self.custom_entries);
+        collector.finish()
+    }
 
-            added += query_res.len();
-            for i in query_res.into_iter().filter(|i| self.item_allowed(i)) {
-                let i = self.apply_rel_mod(i);
-                out.insert(OrderVal::new(i.to_output(), i.get_relevance()));
-                if self.debug {
-                    println!("{:?}: {}", i.to_output(), i.get_relevance());
-                }
+    /// Returns the amount of queries
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.queries.len()
+    }
+
+    /// Returns `true` if there is no query added to the SuggestionTask
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Merges the items of multiple queries into the final results
+pub(crate) struct Collector<'f> {
+    out: UniquePrioContainerMax<OrderVal<UniqueResult, u16>>,
+    /// Amount of items found by all queries so far
+    added: usize,
+    explain: bool,
+    /// Whether to look up the matched key and highlight of each result
+    highlight: bool,
+    /// Whether to add the romaji of the reading to each output
+    romaji: bool,
+    filter: Option<&'f ItemFilter<'f>>,
+    rel_mod: Option<&'f RelMod<'f>>,
+    personalization: Option<&'f Personalization<'f>>,
+}
+
+impl<'f> Collector<'f> {
+    pub(crate) fn new(
+        limit: usize,
+        explain: bool,
+        filter: Option<&'f ItemFilter<'f>>,
+        rel_mod: Option<&'f RelMod<'f>>,
+    ) -> Self {
+        Self {
+            out: UniquePrioContainerMax::new_allocated(limit),
+            added: 0,
+            explain,
+            highlight: true,
+            romaji: false,
+            filter,
+            rel_mod,
+            personalization: None,
+        }
+    }
+
+    #[inline]
+    pub(crate) fn set_highlight(&mut self, highlight: bool) {
+        self.highlight = highlight;
+    }
+
+    #[inline]
+    pub(crate) fn set_romaji(&mut self, romaji: bool) {
+        self.romaji = romaji;
+    }
+
+    /// Boosts words of a users profile
+    #[inline]
+    pub(crate) fn set_personalization(&mut self, personalization: &'f Personalization<'f>) {
+        self.personalization = Some(personalization);
+    }
+
+    /// Returns `true` if a query with the given threshold should run
+    #[inline]
+    pub(crate) fn should_run(&self, threshold: usize) -> bool {
+        threshold == 0 || self.added < threshold
+    }
+
+    /// Adds the items found by a query
+    pub(crate) fn add(&mut self, items: Vec<EngineItem>, query: &str, query_weight: f64) {
+        log::debug!("query {:?} found {} items", query, items.len());
+        self.added += items.len();
+
+        for i in items {
+            if !self.item_allowed(&i) {
+                continue;
             }
+            let res = self.make_result(i, query, query_weight);
+            let relevance = res.relevance;
+            self.out.insert(OrderVal::new(UniqueResult(res), relevance));
         }
+    }
 
-        let cust_add = self
-            .custom_entries
-            .iter()
-            .filter(|i| self.item_allowed(i))
-            .map(|i| self.apply_rel_mod(*i))
-            .map(|i| OrderVal::new(i.to_output(), i.get_relevance()))
-            .inspect(|i| {
-                if self.debug {
-                    println!("{:?}: {}", i.inner(), i.ord());
-                }
-            });
-        out.extend(cust_add);
-
-        let mut out = out.into_iter().map(|i| i.into_inner()).collect::<Vec<_>>();
+    /// Adds custom items which weren't found by a query
+    pub(crate) fn add_custom(&mut self, items: &[EngineItem]) {
+        for i in items {
+            if !self.item_allowed(i) {
+                continue;
+            }
+            let res = self.make_result(*i, "", 1.0);
+            let relevance = res.relevance;
+            self.out.insert(OrderVal::new(UniqueResult(res), relevance));
+        }
+    }
+
+    /// Returns all results ordered by relevance
+    pub(crate) fn finish(self) -> Vec<SuggestionResult> {
+        let mut out = self
+            .out
+            .into_iter()
+            .map(|i| i.into_inner().0)
+            .collect::<Vec<_>>();
         out.reverse();
         out
     }
 
+    /// Applies `rel_mod` and the personalization boost to `item` and converts it into a
+    /// result
+    fn make_result(&self, item: EngineItem, query: &str, query_weight: f64) -> SuggestionResult {
+        let relevance = item.get_relevance();
+        let item = self.apply_rel_mod(item);
+
+        let mut res = if self.highlight {
+            SuggestionResult::new(&item, query)
+        } else {
+            SuggestionResult::without_highlight(&item)
+        };
+        if self.explain {
+            res.explanation = Some(explain(&item, relevance, query_weight));
+        }
+        if self.romaji {
+            res.output.set_romaji();
+        }
+
+        log::trace!("{:?}: {}", res.output, res.relevance);
+        res
+    }
+
     #[inline]
     fn apply_rel_mod<'r>(&self, mut item: EngineItem<'r>) -> EngineItem<'r> {
-        if let Some(ref rel_mod) = self.rel_mod {
+        if let Some(rel_mod) = self.rel_mod {
             let rel = rel_mod(&item, item.get_relevance());
             item.set_relevance(rel);
         }
+        if let Some(personalization) = self.personalization {
+            let boost = personalization.boost(&item);
+            item.set_relevance(item.get_relevance().saturating_add(boost));
+        }
         item
     }
 
     /// Returns `true` if the item `i` should not get filtered out
     #[inline]
     fn item_allowed(&self, i: &EngineItem) -> bool {
-        self.filter.as_ref().map(|f| f(*i.inner())).unwrap_or(true)
+        self.filter.map(|f| f(*i.inner())).unwrap_or(true)
     }
+}
 
-    /// Returns the amount of queries
+/// Returns the components of the relevance of `item`. `relevance` is the relevance before
+/// `rel_mod` and the personalization were applied
+fn explain(item: &EngineItem, relevance: u16, query_weight: f64) -> ScoreExplanation {
+    let mut explanation = match item.score_input() {
+        Some(score) => {
+            let mut explanation = RelevanceCalc::new(score.weights).explain(item, score.str_rel);
+            if query_weight != 0.0 {
+                explanation.extension_weight = score.weights.total_weight / query_weight;
+            }
+            explanation
+        }
+        // Items that weren't ranked by a `RelevanceCalc`, eg. custom entries
+        None => ScoreExplanation {
+            str_rel: 0,
+            str_score: 0.0,
+            freq_score: 0.0,
+            priority_score: 0.0,
+            weights: RelevanceWeights::default(),
+            extension_weight: 1.0,
+            relevance,
+            rel_mod: 0,
+        },
+    };
+    explanation.relevance = relevance;
+    explanation.rel_mod = item.get_relevance() as i32 - relevance as i32;
+    explanation
+}
+
+/// Suggestion result that is unique by its output, so each suggestion only gets returned once
+struct UniqueResult(SuggestionResult);
+
+impl Hash for UniqueResult {
     #[inline]
-    pub fn len(&self) -> usize {
-        self.queries.len()
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.0.output.hash(state);
     }
+}
 
-    /// Returns `true` if there is no query added to the SuggestionTask
+impl PartialEq for UniqueResult {
     #[inline]
-    pub fn is_empty(&self) -> bool {
-        self.len() == 0
+    fn eq(&self, other: &Self) -> bool {
+        self.0.output == other.0.output
+    }
+}
+
+impl Eq for UniqueResult {}
+
+impl PartialOrd for UniqueResult {
+    #[inline]
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for UniqueResult {
+    #[inline]
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.0.output.cmp(&other.0.output)
     }
 }